@@ -3,10 +3,10 @@ use core::ffi::c_int;
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
-use flatten_objects::FlattenObjects;
+use axstd::collections::HashMap;
 use spin::RwLock;
 
-use super::stdio::{stdin, stdout};
+use super::stdio::{stdin, stdout, Stdin, Stdout};
 use crate::ctypes;
 
 pub const AX_FILE_LIMIT: usize = 1024;
@@ -19,36 +19,94 @@ pub trait FileLike: Send + Sync {
     fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync>;
     fn poll(&self) -> LinuxResult<PollState>;
     fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult;
+    /// Whether `O_NONBLOCK` is set, for all the fds that share this file.
+    fn nonblocking(&self) -> bool;
+    /// Number of bytes that can be read without blocking.
+    fn nread(&self) -> LinuxResult<usize> {
+        Ok(0)
+    }
+    /// Absolute path this was opened at, if it lives on a filesystem.
+    fn path(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Flags attached to a file descriptor rather than to the file itself.
+#[derive(Clone, Copy)]
+struct FdFlags {
+    cloexec: bool,
+}
+
+impl FdFlags {
+    const fn new() -> Self {
+        Self { cloexec: false }
+    }
+}
+
+/// An open file descriptor.
+struct FdEntry {
+    file: Arc<dyn FileLike>,
+    flags: FdFlags,
+}
+
+impl FdEntry {
+    fn new(file: Arc<dyn FileLike>, flags: FdFlags) -> Self {
+        Self { file, flags }
+    }
 }
 
 lazy_static::lazy_static! {
-    static ref FD_TABLE: RwLock<FlattenObjects<Arc<dyn FileLike>, AX_FILE_LIMIT>> = {
-        let mut fd_table = FlattenObjects::new();
-        fd_table.add_at(0, Arc::new(stdin()) as _).unwrap(); // stdin
-        fd_table.add_at(1, Arc::new(stdout()) as _).unwrap(); // stdout
-        fd_table.add_at(2, Arc::new(stdout()) as _).unwrap(); // stderr
+    /// Only open descriptors are stored, so a few high-numbered fds stay cheap.
+    static ref FD_TABLE: RwLock<HashMap<c_int, FdEntry>> = {
+        let mut fd_table = HashMap::new();
+        fd_table.insert(0, FdEntry::new(Arc::new(stdin()), FdFlags::new())); // stdin
+        fd_table.insert(1, FdEntry::new(Arc::new(stdout()), FdFlags::new())); // stdout
+        fd_table.insert(2, FdEntry::new(Arc::new(stdout()), FdFlags::new())); // stderr
         RwLock::new(fd_table)
     };
 }
 
+/// Returns the lowest free file descriptor not less than `min_fd`.
+fn alloc_fd(fd_table: &HashMap<c_int, FdEntry>, min_fd: usize) -> LinuxResult<c_int> {
+    (min_fd..AX_FILE_LIMIT)
+        .map(|fd| fd as c_int)
+        .find(|fd| fd_table.get(fd).is_none())
+        .ok_or(LinuxError::EMFILE)
+}
+
+fn fd_flags(fd: c_int) -> LinuxResult<FdFlags> {
+    FD_TABLE
+        .read()
+        .get(&fd)
+        .map(|entry| entry.flags)
+        .ok_or(LinuxError::EBADF)
+}
+
+fn update_fd_flags(fd: c_int, f: impl FnOnce(&mut FdFlags)) -> LinuxResult {
+    let mut fd_table = FD_TABLE.write();
+    let entry = fd_table.get_mut(&fd).ok_or(LinuxError::EBADF)?;
+    f(&mut entry.flags);
+    Ok(())
+}
+
 pub fn get_file_like(fd: c_int) -> LinuxResult<Arc<dyn FileLike>> {
     FD_TABLE
         .read()
-        .get(fd as usize)
-        .cloned()
+        .get(&fd)
+        .map(|entry| entry.file.clone())
         .ok_or(LinuxError::EBADF)
 }
 
 pub fn add_file_like(f: Arc<dyn FileLike>) -> LinuxResult<c_int> {
-    Ok(FD_TABLE.write().add(f).ok_or(LinuxError::EMFILE)? as c_int)
+    let mut fd_table = FD_TABLE.write();
+    let fd = alloc_fd(&fd_table, 0)?;
+    fd_table.insert(fd, FdEntry::new(f, FdFlags::new()));
+    Ok(fd)
 }
 
 pub fn close_file_like(fd: c_int) -> LinuxResult {
-    let f = FD_TABLE
-        .write()
-        .remove(fd as usize)
-        .ok_or(LinuxError::EBADF)?;
-    drop(f);
+    let entry = FD_TABLE.write().remove(&fd).ok_or(LinuxError::EBADF)?;
+    drop(entry);
     Ok(())
 }
 
@@ -62,8 +120,16 @@ pub fn sys_close(fd: c_int) -> c_int {
 }
 
 fn dup_fd(old_fd: c_int) -> LinuxResult<c_int> {
-    let f = get_file_like(old_fd)?;
-    let new_fd = add_file_like(f)?;
+    dup_fd_from(old_fd, 0, false)
+}
+
+/// Duplicate `old_fd` onto the lowest free file descriptor not less than `min_fd`.
+fn dup_fd_from(old_fd: c_int, min_fd: usize, cloexec: bool) -> LinuxResult<c_int> {
+    let mut fd_table = FD_TABLE.write();
+    let old = fd_table.get(&old_fd).ok_or(LinuxError::EBADF)?;
+    let entry = FdEntry::new(old.file.clone(), FdFlags { cloexec });
+    let new_fd = alloc_fd(&fd_table, min_fd)?;
+    fd_table.insert(new_fd, entry);
     Ok(new_fd)
 }
 
@@ -75,7 +141,7 @@ pub fn sys_dup(old_fd: c_int) -> c_int {
 
 /// Duplicate a file descriptor, but it uses the file descriptor number specified in `new_fd`.
 ///
-/// TODO: `dup2` should forcibly close new_fd if it is already opened.
+/// If `new_fd` is already opened, it is closed first.
 pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
     debug!("sys_dup2 <= old_fd: {}, new_fd: {}", old_fd, new_fd);
     syscall_body!(sys_dup2, {
@@ -91,38 +157,60 @@ pub fn sys_dup2(old_fd: c_int, new_fd: c_int) -> c_int {
             return Err(LinuxError::EBADF);
         }
 
-        let f = get_file_like(old_fd)?;
-        FD_TABLE
-            .write()
-            .add_at(new_fd as usize, f)
-            .ok_or(LinuxError::EMFILE)?;
+        let mut fd_table = FD_TABLE.write();
+        let old = fd_table.get(&old_fd).ok_or(LinuxError::EBADF)?;
+        let entry = FdEntry::new(old.file.clone(), FdFlags::new());
+        fd_table.insert(new_fd, entry);
 
         Ok(new_fd)
     })
 }
 
+/// Check whether `fd` refers to the console.
+///
+/// Return 1 if it does, otherwise return 0.
+pub fn sys_isatty(fd: c_int) -> c_int {
+    debug!("sys_isatty <= {}", fd);
+    syscall_body!(sys_isatty, {
+        let f = get_file_like(fd)?.into_any();
+        Ok((f.is::<Stdin>() || f.is::<Stdout>()) as c_int)
+    })
+}
+
 /// Manipulate file descriptor.
 ///
-/// TODO: `SET/GET` command is ignored, hard-code stdin/stdout
+/// The close-on-exec flag is only recorded, and `F_GETFL` reports `O_NONBLOCK`
+/// alone as the access mode is not tracked. Unlike close-on-exec, `O_NONBLOCK`
+/// belongs to the file, so it is shared with the duplicates of `fd`.
 pub fn sys_fcntl(fd: c_int, cmd: c_int, arg: usize) -> c_int {
     debug!("sys_fcntl <= fd: {} cmd: {} arg: {}", fd, cmd, arg);
     syscall_body!(sys_fcntl, {
+        let f = get_file_like(fd)?;
         match cmd as u32 {
-            ctypes::F_DUPFD => dup_fd(fd),
-            ctypes::F_DUPFD_CLOEXEC => {
-                // TODO: Change fd flags
-                dup_fd(fd)
+            ctypes::F_DUPFD => dup_fd_from(fd, arg, false),
+            ctypes::F_DUPFD_CLOEXEC => dup_fd_from(fd, arg, true),
+            ctypes::F_GETFD => Ok(if fd_flags(fd)?.cloexec {
+                ctypes::FD_CLOEXEC as c_int
+            } else {
+                0
+            }),
+            ctypes::F_SETFD => {
+                let cloexec = arg & (ctypes::FD_CLOEXEC as usize) != 0;
+                update_fd_flags(fd, |flags| flags.cloexec = cloexec)?;
+                Ok(0)
             }
+            ctypes::F_GETFL => Ok(if f.nonblocking() {
+                ctypes::O_NONBLOCK as c_int
+            } else {
+                0
+            }),
             ctypes::F_SETFL => {
-                if fd == 0 || fd == 1 || fd == 2 {
-                    return Ok(0);
-                }
-                get_file_like(fd)?.set_nonblocking(arg & (ctypes::O_NONBLOCK as usize) > 0)?;
+                f.set_nonblocking(arg & (ctypes::O_NONBLOCK as usize) != 0)?;
                 Ok(0)
             }
             _ => {
                 warn!("unsupported fcntl parameters: cmd {}", cmd);
-                Ok(0)
+                Err(LinuxError::EINVAL)
             }
         }
     })
@@ -1,22 +1,88 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 use alloc::sync::Arc;
-use core::ffi::{c_char, c_int};
+use core::ffi::{c_char, c_int, c_void};
+use core::sync::atomic::{AtomicBool, Ordering};
 
-use axerrno::{LinuxError, LinuxResult};
-use axfs::fops::OpenOptions;
+use axerrno::{AxError, LinuxError, LinuxResult};
+use axfs::fops::{DirEntry, OpenOptions};
 use axio::{PollState, SeekFrom};
 use axsync::Mutex;
 
 use super::fd_ops::{get_file_like, FileLike};
-use crate::{ctypes, utils::char_ptr_to_str};
+use crate::{ctypes, utils::{ax_to_linux, char_ptr_to_str}};
+
+/// Permission bits that [`sys_open`] gave to the files it created, and those
+/// recorded by [`record_file_mode`], by path.
+///
+/// The filesystems do not record permissions, so the bits are kept here and
+/// reported by `stat` instead. The keys are canonical paths, which
+/// [`move_file_mode`] follows across renames until [`forget_file_mode`] is
+/// called on removal.
+static CREATED_MODES: Mutex<BTreeMap<String, u32>> = Mutex::new(BTreeMap::new());
+
+/// Records the permission bits of the file or directory just created at
+/// `path`, to be reported by `stat` from now on.
+pub fn record_file_mode(path: &str, mode: u32) {
+    if let Ok(path) = axfs::api::canonicalize(path) {
+        CREATED_MODES
+            .lock()
+            .insert(path.trim_end_matches('/').into(), mode & 0o7777);
+    }
+}
+
+/// Drops the permission bits recorded for the file at `path` by [`sys_open`]
+/// or [`record_file_mode`].
+pub fn forget_file_mode(path: &str) {
+    if let Ok(path) = axfs::api::canonicalize(path) {
+        CREATED_MODES.lock().remove(path.trim_end_matches('/'));
+    }
+}
+
+/// Moves the permission bits recorded for `old` to `new` after a rename,
+/// along with those of the files below `old` if it is a directory.
+///
+/// Bits recorded for a file that the rename replaced at `new` are dropped.
+pub fn move_file_mode(old: &str, new: &str) {
+    let (Ok(old), Ok(new)) = (axfs::api::canonicalize(old), axfs::api::canonicalize(new)) else {
+        return;
+    };
+    let old = old.trim_end_matches('/');
+    let new = new.trim_end_matches('/');
+    if old == new {
+        return;
+    }
+    let mut modes = CREATED_MODES.lock();
+    modes.remove(new);
+    let moved: Vec<String> = modes
+        .keys()
+        .filter(|path| {
+            path.strip_prefix(old)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .cloned()
+        .collect();
+    for path in moved {
+        if let Some(mode) = modes.remove(&path) {
+            modes.insert(format!("{}{}", new, &path[old.len()..]), mode);
+        }
+    }
+}
 
 pub struct File {
     inner: Mutex<axfs::fops::File>,
+    path: String,
+    nonblocking: AtomicBool,
 }
 
 impl File {
-    fn new(inner: axfs::fops::File) -> Self {
+    fn new(inner: axfs::fops::File, path: String) -> Self {
         Self {
             inner: Mutex::new(inner),
+            path,
+            nonblocking: AtomicBool::new(false),
         }
     }
 
@@ -34,17 +100,21 @@ impl File {
 
 impl FileLike for File {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
-        Ok(self.inner.lock().read(buf)?)
+        Ok(self.inner.lock().read(buf).map_err(ax_to_linux)?)
     }
 
     fn write(&self, buf: &[u8]) -> LinuxResult<usize> {
-        Ok(self.inner.lock().write(buf)?)
+        Ok(self.inner.lock().write(buf).map_err(ax_to_linux)?)
     }
 
     fn stat(&self) -> LinuxResult<ctypes::stat> {
-        let metadata = self.inner.lock().get_attr()?;
+        let metadata = self.inner.lock().get_attr().map_err(ax_to_linux)?;
         let ty = metadata.file_type() as u8;
-        let perm = metadata.perm().bits() as u32;
+        let perm = CREATED_MODES
+            .lock()
+            .get(&self.path)
+            .copied()
+            .unwrap_or(metadata.perm().bits() as u32);
         let st_mode = ((ty as u32) << 12) | perm;
         Ok(ctypes::stat {
             st_ino: 1,
@@ -70,9 +140,122 @@ impl FileLike for File {
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
+        Ok(())
+    }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn nread(&self) -> LinuxResult<usize> {
+        let mut file = self.inner.lock();
+        let pos = file.seek(SeekFrom::Current(0)).map_err(ax_to_linux)?;
+        Ok(file.get_attr().map_err(ax_to_linux)?.size().saturating_sub(pos) as usize)
+    }
+
+    fn path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
+}
+
+/// Size of the fixed part of `struct linux_dirent64` before `d_name`.
+const DIRENT64_HEADER_LEN: usize = 19;
+
+pub struct Directory {
+    inner: Mutex<DirState>,
+    path: String,
+    nonblocking: AtomicBool,
+}
+
+struct DirState {
+    dir: axfs::fops::Directory,
+    /// Number of entries already returned to the user.
+    pos: usize,
+    /// An entry read from the directory that did not fit into the last buffer.
+    pending: Option<DirEntry>,
+}
+
+impl Directory {
+    fn new(dir: axfs::fops::Directory, path: String) -> Self {
+        Self {
+            inner: Mutex::new(DirState {
+                dir,
+                pos: 0,
+                pending: None,
+            }),
+            path,
+            nonblocking: AtomicBool::new(false),
+        }
+    }
+
+    fn add_to_fd_table(self) -> LinuxResult<c_int> {
+        super::fd_ops::add_file_like(Arc::new(self))
+    }
+
+    fn from_fd(fd: c_int) -> LinuxResult<Arc<Self>> {
+        let f = super::fd_ops::get_file_like(fd)?;
+        f.into_any()
+            .downcast::<Self>()
+            .map_err(|_| LinuxError::ENOTDIR)
+    }
+}
+
+impl FileLike for Directory {
+    fn read(&self, _buf: &mut [u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EISDIR)
+    }
+
+    fn write(&self, _buf: &[u8]) -> LinuxResult<usize> {
+        Err(LinuxError::EBADF)
+    }
+
+    fn stat(&self) -> LinuxResult<ctypes::stat> {
+        let metadata = self.inner.lock().dir.get_attr().map_err(ax_to_linux)?;
+        let ty = metadata.file_type() as u8;
+        let perm = CREATED_MODES
+            .lock()
+            .get(self.path.trim_end_matches('/'))
+            .copied()
+            .unwrap_or(metadata.perm().bits() as u32);
+        let st_mode = ((ty as u32) << 12) | perm;
+        Ok(ctypes::stat {
+            st_ino: 1,
+            st_nlink: 1,
+            st_mode,
+            st_uid: 1000,
+            st_gid: 1000,
+            st_size: metadata.size() as _,
+            st_blocks: metadata.blocks() as _,
+            st_blksize: 512,
+            ..Default::default()
+        })
+    }
+
+    fn into_any(self: Arc<Self>) -> Arc<dyn core::any::Any + Send + Sync> {
+        self
+    }
+
+    fn poll(&self) -> LinuxResult<PollState> {
+        Ok(PollState {
+            readable: true,
+            writable: false,
+        })
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
         Ok(())
     }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
 }
 
 /// Convert open flags to [`OpenOptions`].
@@ -106,13 +289,34 @@ fn flags_to_options(flags: c_int, _mode: ctypes::mode_t) -> OpenOptions {
 ///
 /// Return its index in the file table (`fd`). Return `EMFILE` if it already
 /// has the maximum number of files open.
+///
+/// `mode` only applies to a file created by `O_CREAT`, and is ignored if the
+/// file already exists.
 pub fn sys_open(filename: *const c_char, flags: c_int, mode: ctypes::mode_t) -> c_int {
     let filename = char_ptr_to_str(filename);
     debug!("sys_open <= {:?} {:#o} {:#o}", filename, flags, mode);
     syscall_body!(sys_open, {
+        let filename = filename?;
         let options = flags_to_options(flags, mode);
-        let file = axfs::fops::File::open(filename?, &options)?;
-        File::new(file).add_to_fd_table()
+        let path = axfs::api::canonicalize(filename).map_err(ax_to_linux)?;
+        let existing = axfs::api::metadata(filename).ok();
+        let is_dir = existing.as_ref().is_some_and(|m| m.is_dir());
+        if is_dir || flags as u32 & ctypes::O_DIRECTORY != 0 {
+            if flags as u32 & 0b11 != ctypes::O_RDONLY {
+                return Err(if is_dir {
+                    LinuxError::EISDIR
+                } else {
+                    LinuxError::EINVAL
+                });
+            }
+            let dir = axfs::fops::Directory::open_dir(filename, &options).map_err(ax_to_linux)?;
+            return Directory::new(dir, path).add_to_fd_table();
+        }
+        let file = axfs::fops::File::open(filename, &options).map_err(ax_to_linux)?;
+        if existing.is_none() && flags as u32 & ctypes::O_CREAT != 0 {
+            CREATED_MODES.lock().insert(path.clone(), mode & 0o7777);
+        }
+        File::new(file, path).add_to_fd_table()
     })
 }
 
@@ -128,11 +332,142 @@ pub fn sys_lseek(fd: c_int, offset: ctypes::off_t, whence: c_int) -> ctypes::off
             2 => SeekFrom::End(offset as _),
             _ => return Err(LinuxError::EINVAL),
         };
-        let off = File::from_fd(fd)?.inner.lock().seek(pos)?;
+        let off = File::from_fd(fd)?.inner.lock().seek(pos).map_err(ax_to_linux)?;
         Ok(off)
     })
 }
 
+/// Read data from the file indicated by `fd` at the given `offset`.
+///
+/// It does not change the file position. Return the read size if success.
+pub fn sys_pread(
+    fd: c_int,
+    buf: *mut c_void,
+    count: usize,
+    offset: ctypes::off_t,
+) -> ctypes::ssize_t {
+    debug!("sys_pread <= {} {:#x} {} {}", fd, buf as usize, count, offset);
+    syscall_body!(sys_pread, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        if offset < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, count) };
+        let read_len = File::from_fd(fd)?.inner.lock().read_at(offset as u64, dst).map_err(ax_to_linux)?;
+        Ok(read_len as ctypes::ssize_t)
+    })
+}
+
+/// Write data to the file indicated by `fd` at the given `offset`.
+///
+/// It does not change the file position. Return the written size if success.
+pub fn sys_pwrite(
+    fd: c_int,
+    buf: *const c_void,
+    count: usize,
+    offset: ctypes::off_t,
+) -> ctypes::ssize_t {
+    debug!("sys_pwrite <= {} {:#x} {} {}", fd, buf as usize, count, offset);
+    syscall_body!(sys_pwrite, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        if offset < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let src = unsafe { core::slice::from_raw_parts(buf as *const u8, count) };
+        let write_len = File::from_fd(fd)?.inner.lock().write_at(offset as u64, src).map_err(ax_to_linux)?;
+        Ok(write_len as ctypes::ssize_t)
+    })
+}
+
+/// Truncate the file indicated by `fd` to `length` bytes.
+///
+/// The file is extended with zeros if it was shorter. Return 0 if success.
+pub fn sys_ftruncate(fd: c_int, length: ctypes::off_t) -> c_int {
+    debug!("sys_ftruncate <= {} {}", fd, length);
+    syscall_body!(sys_ftruncate, {
+        if length < 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        File::from_fd(fd)?
+            .inner
+            .lock()
+            .truncate(length as u64)
+            .map_err(|e| match e {
+                // not opened for writing
+                AxError::PermissionDenied => LinuxError::EINVAL,
+                e => ax_to_linux(e),
+            })?;
+        Ok(0)
+    })
+}
+
+/// Flush the buffered data of the file indicated by `fd` to the backing store.
+///
+/// Return 0 if success.
+pub fn sys_fsync(fd: c_int) -> c_int {
+    debug!("sys_fsync <= {}", fd);
+    syscall_body!(sys_fsync, {
+        match File::from_fd(fd)?.inner.lock().flush() {
+            // nothing to flush for a file not opened for writing
+            Ok(()) | Err(AxError::PermissionDenied) => Ok(0),
+            Err(e) => Err(ax_to_linux(e)),
+        }
+    })
+}
+
+/// Read the entries of the directory indicated by `fd` into `buf` as
+/// `struct linux_dirent64` records.
+///
+/// Return the number of bytes written, or 0 at the end of the directory.
+pub unsafe fn sys_getdents64(fd: c_int, buf: *mut c_void, len: usize) -> ctypes::ssize_t {
+    debug!("sys_getdents64 <= {} {:#x} {}", fd, buf as usize, len);
+    syscall_body!(sys_getdents64, {
+        if buf.is_null() {
+            return Err(LinuxError::EFAULT);
+        }
+        let dir = Directory::from_fd(fd)?;
+        let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, len) };
+        let mut state = dir.inner.lock();
+        let mut written = 0;
+        loop {
+            let entry = match state.pending.take() {
+                Some(entry) => entry,
+                None => {
+                    let mut entries = [DirEntry::default()];
+                    if state.dir.read_dir(&mut entries).map_err(ax_to_linux)? == 0 {
+                        break;
+                    }
+                    let [entry] = entries;
+                    entry
+                }
+            };
+            let name = entry.name_as_bytes();
+            let reclen = (DIRENT64_HEADER_LEN + name.len() + 1).next_multiple_of(8);
+            if written + reclen > len {
+                state.pending = Some(entry);
+                break;
+            }
+            state.pos += 1;
+            let rec = &mut dst[written..written + reclen];
+            rec.fill(0);
+            rec[0..8].copy_from_slice(&(state.pos as u64).to_ne_bytes()); // d_ino
+            rec[8..16].copy_from_slice(&(state.pos as i64).to_ne_bytes()); // d_off
+            rec[16..18].copy_from_slice(&(reclen as u16).to_ne_bytes()); // d_reclen
+            rec[18] = entry.entry_type() as u8; // d_type, same encoding as `DT_*`
+            rec[DIRENT64_HEADER_LEN..DIRENT64_HEADER_LEN + name.len()].copy_from_slice(name);
+            written += reclen;
+        }
+        if written == 0 && state.pending.is_some() {
+            return Err(LinuxError::EINVAL);
+        }
+        Ok(written)
+    })
+}
+
 /// Get the file metadata by `path` and write into `buf`.
 ///
 /// Return 0 if success.
@@ -145,8 +480,10 @@ pub unsafe fn sys_stat(path: *const c_char, buf: *mut ctypes::stat) -> c_int {
         }
         let mut options = OpenOptions::new();
         options.read(true);
-        let file = axfs::fops::File::open(path?, &options)?;
-        let st = File::new(file).stat()?;
+        let path = path?;
+        let file = axfs::fops::File::open(path, &options).map_err(ax_to_linux)?;
+        let path = axfs::api::canonicalize(path).map_err(ax_to_linux)?;
+        let st = File::new(file, path).stat()?;
         unsafe { *buf = st };
         Ok(0)
     })
@@ -190,7 +527,7 @@ pub fn sys_getcwd(buf: *mut c_char, size: usize) -> *mut c_char {
             return Ok(core::ptr::null::<c_char>() as _);
         }
         let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, size as _) };
-        let cwd = axfs::api::current_dir()?;
+        let cwd = axfs::api::current_dir().map_err(ax_to_linux)?;
         let cwd = cwd.as_bytes();
         if cwd.len() < size {
             dst[..cwd.len()].copy_from_slice(cwd);
@@ -211,7 +548,8 @@ pub fn sys_rename(old: *const c_char, new: *const c_char) -> c_int {
         let old_path = char_ptr_to_str(old)?;
         let new_path = char_ptr_to_str(new)?;
         debug!("sys_rename <= old: {:?}, new: {:?}", old_path, new_path);
-        axfs::api::rename(old_path, new_path)?;
+        axfs::api::rename(old_path, new_path).map_err(ax_to_linux)?;
+        move_file_mode(old_path, new_path);
         Ok(0)
     })
 }
@@ -1,5 +1,5 @@
 use crate::ctypes;
-use axerrno::LinuxError;
+use axerrno::{LinuxError, LinuxResult};
 use core::ffi::{c_int, c_void};
 
 #[cfg(feature = "fd")]
@@ -7,6 +7,32 @@ use crate::imp::fd_ops::get_file_like;
 #[cfg(not(feature = "fd"))]
 use axio::prelude::*;
 
+fn read_fd(fd: c_int, dst: &mut [u8]) -> LinuxResult<usize> {
+    #[cfg(feature = "fd")]
+    {
+        get_file_like(fd)?.read(dst)
+    }
+    #[cfg(not(feature = "fd"))]
+    match fd {
+        0 => Ok(super::stdio::stdin().read(dst)?),
+        1 | 2 => Err(LinuxError::EPERM),
+        _ => Err(LinuxError::EBADF),
+    }
+}
+
+fn write_fd(fd: c_int, src: &[u8]) -> LinuxResult<usize> {
+    #[cfg(feature = "fd")]
+    {
+        get_file_like(fd)?.write(src)
+    }
+    #[cfg(not(feature = "fd"))]
+    match fd {
+        0 => Err(LinuxError::EPERM),
+        1 | 2 => Ok(super::stdio::stdout().write(src)?),
+        _ => Err(LinuxError::EBADF),
+    }
+}
+
 /// Read data from the file indicated by `fd`.
 ///
 /// Return the read size if success.
@@ -17,16 +43,7 @@ pub fn sys_read(fd: c_int, buf: *mut c_void, count: usize) -> ctypes::ssize_t {
             return Err(LinuxError::EFAULT);
         }
         let dst = unsafe { core::slice::from_raw_parts_mut(buf as *mut u8, count) };
-        #[cfg(feature = "fd")]
-        {
-            Ok(get_file_like(fd)?.read(dst)? as ctypes::ssize_t)
-        }
-        #[cfg(not(feature = "fd"))]
-        match fd {
-            0 => Ok(super::stdio::stdin().read(dst)? as ctypes::ssize_t),
-            1 | 2 => Err(LinuxError::EPERM),
-            _ => Err(LinuxError::EBADF),
-        }
+        Ok(read_fd(fd, dst)? as ctypes::ssize_t)
     })
 }
 
@@ -40,33 +57,85 @@ pub fn sys_write(fd: c_int, buf: *const c_void, count: usize) -> ctypes::ssize_t
             return Err(LinuxError::EFAULT);
         }
         let src = unsafe { core::slice::from_raw_parts(buf as *const u8, count) };
-        #[cfg(feature = "fd")]
-        {
-            Ok(get_file_like(fd)?.write(src)? as ctypes::ssize_t)
-        }
-        #[cfg(not(feature = "fd"))]
-        match fd {
-            0 => Err(LinuxError::EPERM),
-            1 | 2 => Ok(super::stdio::stdout().write(src)? as ctypes::ssize_t),
-            _ => Err(LinuxError::EBADF),
-        }
+        Ok(write_fd(fd, src)? as ctypes::ssize_t)
     })
 }
 
 /// Write a vector.
+///
+/// Buffers are written in order; it stops early on a short write, and returns
+/// the total size written so far. An error is only reported if nothing has
+/// been written.
 pub unsafe fn sys_writev(fd: c_int, iov: *const ctypes::iovec, iocnt: c_int) -> ctypes::ssize_t {
     debug!("sys_writev <= fd: {}", fd);
     syscall_body!(sys_writev, {
         if !(0..=1024).contains(&iocnt) {
             return Err(LinuxError::EINVAL);
         }
+        if iov.is_null() && iocnt > 0 {
+            return Err(LinuxError::EFAULT);
+        }
+
+        let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
+        let mut ret = 0;
+        for iov in iovs.iter() {
+            if iov.iov_len == 0 {
+                continue;
+            }
+            if iov.iov_base.is_null() {
+                return Err(LinuxError::EFAULT);
+            }
+            let src = unsafe { core::slice::from_raw_parts(iov.iov_base as *const u8, iov.iov_len) };
+            let n = match write_fd(fd, src) {
+                Ok(n) => n,
+                Err(_) if ret > 0 => break,
+                Err(e) => return Err(e),
+            };
+            ret += n;
+            if n < iov.iov_len {
+                break;
+            }
+        }
+
+        Ok(ret as ctypes::ssize_t)
+    })
+}
+
+/// Read a vector.
+///
+/// Buffers are filled in order; it stops early at end of file or on a short
+/// read, and returns the total size read so far.
+pub unsafe fn sys_readv(fd: c_int, iov: *const ctypes::iovec, iocnt: c_int) -> ctypes::ssize_t {
+    debug!("sys_readv <= fd: {}", fd);
+    syscall_body!(sys_readv, {
+        if !(0..=1024).contains(&iocnt) {
+            return Err(LinuxError::EINVAL);
+        }
+        if iov.is_null() && iocnt > 0 {
+            return Err(LinuxError::EFAULT);
+        }
 
         let iovs = unsafe { core::slice::from_raw_parts(iov, iocnt as usize) };
         let mut ret = 0;
         for iov in iovs.iter() {
-            ret += sys_write(fd, iov.iov_base, iov.iov_len);
+            if iov.iov_len == 0 {
+                continue;
+            }
+            if iov.iov_base.is_null() {
+                return Err(LinuxError::EFAULT);
+            }
+            let dst = unsafe { core::slice::from_raw_parts_mut(iov.iov_base as *mut u8, iov.iov_len) };
+            let n = match read_fd(fd, dst) {
+                Ok(n) => n,
+                Err(_) if ret > 0 => break,
+                Err(e) => return Err(e),
+            };
+            ret += n;
+            if n < iov.iov_len {
+                break;
+            }
         }
 
-        Ok(ret)
+        Ok(ret as ctypes::ssize_t)
     })
 }
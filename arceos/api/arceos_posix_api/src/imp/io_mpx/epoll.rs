@@ -5,6 +5,7 @@
 use alloc::collections::btree_map::Entry;
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, Ordering};
 use core::{ffi::c_int, time::Duration};
 
 use axerrno::{LinuxError, LinuxResult};
@@ -16,6 +17,7 @@ use crate::imp::fd_ops::{add_file_like, get_file_like, FileLike};
 
 pub struct EpollInstance {
     events: Mutex<BTreeMap<usize, ctypes::epoll_event>>,
+    nonblocking: AtomicBool,
 }
 
 unsafe impl Send for ctypes::epoll_event {}
@@ -26,6 +28,7 @@ impl EpollInstance {
     pub fn new(_flags: usize) -> Self {
         Self {
             events: Mutex::new(BTreeMap::new()),
+            nonblocking: AtomicBool::new(false),
         }
     }
 
@@ -132,9 +135,14 @@ impl FileLike for EpollInstance {
         Err(LinuxError::ENOSYS)
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
         Ok(())
     }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
 }
 
 /// Creates a new epoll instance.
@@ -168,6 +168,13 @@ impl FileLike for Socket {
         }
         Ok(())
     }
+
+    fn nonblocking(&self) -> bool {
+        match self {
+            Socket::Udp(udpsocket) => udpsocket.lock().is_nonblocking(),
+            Socket::Tcp(tcpsocket) => tcpsocket.lock().is_nonblocking(),
+        }
+    }
 }
 
 impl From<SocketAddrV4> for ctypes::sockaddr_in {
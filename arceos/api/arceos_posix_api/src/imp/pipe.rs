@@ -1,5 +1,6 @@
 use alloc::sync::Arc;
 use core::ffi::c_int;
+use core::sync::atomic::{AtomicBool, Ordering};
 
 use axerrno::{LinuxError, LinuxResult};
 use axio::PollState;
@@ -17,6 +18,21 @@ enum RingBufferStatus {
 
 const RING_BUFFER_SIZE: usize = 256;
 
+static INTERRUPT_CHECK: spin::Once<fn() -> bool> = spin::Once::new();
+
+/// Registers `check`, which a blocked pipe read or write calls each time it
+/// retries. Once it returns `true`, the operation gives up with `EINTR`, or
+/// with the partial count if some bytes have already been transferred.
+///
+/// Only the first registration takes effect.
+pub fn set_interrupt_check(check: fn() -> bool) {
+    INTERRUPT_CHECK.call_once(|| check);
+}
+
+fn interrupted() -> bool {
+    INTERRUPT_CHECK.get().is_some_and(|check| check())
+}
+
 pub struct PipeRingBuffer {
     arr: [u8; RING_BUFFER_SIZE],
     head: usize,
@@ -76,6 +92,7 @@ impl PipeRingBuffer {
 
 pub struct Pipe {
     readable: bool,
+    nonblocking: AtomicBool,
     buffer: Arc<Mutex<PipeRingBuffer>>,
 }
 
@@ -84,10 +101,12 @@ impl Pipe {
         let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
         let read_end = Pipe {
             readable: true,
+            nonblocking: AtomicBool::new(false),
             buffer: buffer.clone(),
         };
         let write_end = Pipe {
             readable: false,
+            nonblocking: AtomicBool::new(false),
             buffer,
         };
         (read_end, write_end)
@@ -117,9 +136,15 @@ impl FileLike for Pipe {
             let mut ring_buffer = self.buffer.lock();
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
-                if self.write_end_close() {
+                if read_size > 0 || self.write_end_close() {
                     return Ok(read_size);
                 }
+                if self.nonblocking() {
+                    return Err(LinuxError::EAGAIN);
+                }
+                if interrupted() {
+                    return Err(LinuxError::EINTR);
+                }
                 drop(ring_buffer);
                 // Data not ready, wait for write end
                 crate::sys_sched_yield(); // TODO: use synconize primitive
@@ -145,6 +170,23 @@ impl FileLike for Pipe {
             let mut ring_buffer = self.buffer.lock();
             let loop_write = ring_buffer.available_write();
             if loop_write == 0 {
+                if write_size == max_len {
+                    return Ok(write_size);
+                }
+                if self.nonblocking() {
+                    return if write_size > 0 {
+                        Ok(write_size)
+                    } else {
+                        Err(LinuxError::EAGAIN)
+                    };
+                }
+                if interrupted() {
+                    return if write_size > 0 {
+                        Ok(write_size)
+                    } else {
+                        Err(LinuxError::EINTR)
+                    };
+                }
                 drop(ring_buffer);
                 // Buffer is full, wait for read end to consume
                 crate::sys_sched_yield(); // TODO: use synconize primitive
@@ -185,9 +227,21 @@ impl FileLike for Pipe {
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        self.nonblocking.store(nonblocking, Ordering::Release);
         Ok(())
     }
+
+    fn nonblocking(&self) -> bool {
+        self.nonblocking.load(Ordering::Acquire)
+    }
+
+    fn nread(&self) -> LinuxResult<usize> {
+        if !self.readable() {
+            return Ok(0);
+        }
+        Ok(self.buffer.lock().available_read())
+    }
 }
 
 /// Create a pipe
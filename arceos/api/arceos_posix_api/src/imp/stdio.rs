@@ -4,6 +4,8 @@ use axsync::Mutex;
 
 #[cfg(feature = "fd")]
 use {alloc::sync::Arc, axerrno::LinuxError, axerrno::LinuxResult, axio::PollState};
+#[cfg(feature = "fd")]
+use core::sync::atomic::{AtomicBool, Ordering};
 
 fn console_read_bytes() -> Option<u8> {
     axhal::console::getchar().map(|c| if c == b'\r' { b'\n' } else { c })
@@ -97,6 +99,13 @@ pub fn stdout() -> Stdout {
     Stdout { inner: &INSTANCE }
 }
 
+/// `O_NONBLOCK` of the standard input, shared by all its handles.
+#[cfg(feature = "fd")]
+static STDIN_NONBLOCKING: AtomicBool = AtomicBool::new(false);
+/// `O_NONBLOCK` of the standard output, shared by all its handles.
+#[cfg(feature = "fd")]
+static STDOUT_NONBLOCKING: AtomicBool = AtomicBool::new(false);
+
 #[cfg(feature = "fd")]
 impl super::fd_ops::FileLike for Stdin {
     fn read(&self, buf: &mut [u8]) -> LinuxResult<usize> {
@@ -128,9 +137,14 @@ impl super::fd_ops::FileLike for Stdin {
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        STDIN_NONBLOCKING.store(nonblocking, Ordering::Release);
         Ok(())
     }
+
+    fn nonblocking(&self) -> bool {
+        STDIN_NONBLOCKING.load(Ordering::Acquire)
+    }
 }
 
 #[cfg(feature = "fd")]
@@ -164,7 +178,12 @@ impl super::fd_ops::FileLike for Stdout {
         })
     }
 
-    fn set_nonblocking(&self, _nonblocking: bool) -> LinuxResult {
+    fn set_nonblocking(&self, nonblocking: bool) -> LinuxResult {
+        STDOUT_NONBLOCKING.store(nonblocking, Ordering::Release);
         Ok(())
     }
+
+    fn nonblocking(&self) -> bool {
+        STDOUT_NONBLOCKING.load(Ordering::Acquire)
+    }
 }
@@ -19,6 +19,8 @@ mod utils;
 
 mod imp;
 
+pub use utils::ax_to_linux;
+
 /// Platform-specific constants and parameters.
 pub mod config {
     pub use axconfig::*;
@@ -30,16 +32,20 @@ pub mod config {
 #[allow(dead_code, non_snake_case, non_camel_case_types, non_upper_case_globals, clippy::upper_case_acronyms, missing_docs)]
 pub mod ctypes;
 
-pub use imp::io::{sys_read, sys_write, sys_writev};
+pub use imp::io::{sys_read, sys_readv, sys_write, sys_writev};
 pub use imp::resources::{sys_getrlimit, sys_setrlimit};
 pub use imp::sys::sys_sysconf;
 pub use imp::task::{sys_exit, sys_getpid, sys_sched_yield};
 pub use imp::time::{sys_clock_gettime, sys_nanosleep};
 
 #[cfg(feature = "fd")]
-pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl, get_file_like};
+pub use imp::fd_ops::{sys_close, sys_dup, sys_dup2, sys_fcntl, sys_isatty, get_file_like, AX_FILE_LIMIT};
 #[cfg(feature = "fs")]
-pub use imp::fs::{sys_fstat, sys_getcwd, sys_lseek, sys_lstat, sys_open, sys_rename, sys_stat};
+pub use imp::fs::{
+    forget_file_mode, move_file_mode, record_file_mode, sys_fstat, sys_fsync, sys_ftruncate,
+    sys_getcwd, sys_getdents64, sys_lseek, sys_lstat, sys_open, sys_pread, sys_pwrite, sys_rename,
+    sys_stat,
+};
 #[cfg(feature = "select")]
 pub use imp::io_mpx::sys_select;
 #[cfg(feature = "epoll")]
@@ -51,7 +57,7 @@ pub use imp::net::{
     sys_socket,
 };
 #[cfg(feature = "pipe")]
-pub use imp::pipe::sys_pipe;
+pub use imp::pipe::{set_interrupt_check, sys_pipe};
 #[cfg(feature = "multitask")]
 pub use imp::pthread::mutex::{
     sys_pthread_mutex_init, sys_pthread_mutex_lock, sys_pthread_mutex_unlock,
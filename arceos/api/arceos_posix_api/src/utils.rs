@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 #![allow(unused_macros)]
 
-use axerrno::{LinuxError, LinuxResult};
+use axerrno::{AxError, LinuxError, LinuxResult};
 use core::ffi::{c_char, CStr};
 
 pub fn char_ptr_to_str<'a>(str: *const c_char) -> LinuxResult<&'a str> {
@@ -30,6 +30,37 @@ pub fn check_null_mut_ptr<T>(ptr: *mut T) -> LinuxResult {
     }
 }
 
+/// Translates an error of the ArceOS modules into the errno of a syscall.
+///
+/// Every syscall goes through this instead of the `From` conversion of
+/// `axerrno`, so that each `AxError` has one errno here.
+pub const fn ax_to_linux(e: AxError) -> LinuxError {
+    match e {
+        AxError::AddrInUse => LinuxError::EADDRINUSE,
+        AxError::AlreadyExists => LinuxError::EEXIST,
+        AxError::BadAddress => LinuxError::EFAULT,
+        AxError::BadState => LinuxError::EINVAL,
+        AxError::ConnectionRefused => LinuxError::ECONNREFUSED,
+        AxError::ConnectionReset => LinuxError::ECONNRESET,
+        AxError::DirectoryNotEmpty => LinuxError::ENOTEMPTY,
+        AxError::InvalidData => LinuxError::EINVAL,
+        AxError::InvalidInput => LinuxError::EINVAL,
+        AxError::Io => LinuxError::EIO,
+        AxError::IsADirectory => LinuxError::EISDIR,
+        AxError::NoMemory => LinuxError::ENOMEM,
+        AxError::NotADirectory => LinuxError::ENOTDIR,
+        AxError::NotConnected => LinuxError::ENOTCONN,
+        AxError::NotFound => LinuxError::ENOENT,
+        AxError::PermissionDenied => LinuxError::EACCES,
+        AxError::ResourceBusy => LinuxError::EBUSY,
+        AxError::StorageFull => LinuxError::ENOSPC,
+        AxError::UnexpectedEof => LinuxError::EIO,
+        AxError::Unsupported => LinuxError::ENOSYS,
+        AxError::WouldBlock => LinuxError::EAGAIN,
+        AxError::WriteZero => LinuxError::EIO,
+    }
+}
+
 macro_rules! syscall_body {
     ($fn: ident, $($stmt: tt)*) => {{
         #[allow(clippy::redundant_closure_call)]
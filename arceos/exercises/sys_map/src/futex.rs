@@ -0,0 +1,123 @@
+//! Futex wait queues keyed by user address.
+//!
+//! All threads share one address space, so the user virtual address alone
+//! identifies a futex word. A queue only exists while a thread waits on it.
+
+use alloc::collections::BTreeMap;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use core::time::Duration;
+
+use axerrno::{LinuxError, LinuxResult};
+use axsync::Mutex;
+use axtask::{TaskExtRef, WaitQueue};
+
+struct FutexQueue {
+    wq: WaitQueue,
+    /// Bumped on every wake, so waiters can tell a wake from a spurious one.
+    seq: AtomicUsize,
+    /// The threads in [`futex_wait`] on this queue, only changed with
+    /// [`FUTEX_QUEUES`] locked.
+    waiters: AtomicUsize,
+}
+
+static FUTEX_QUEUES: Mutex<BTreeMap<usize, Arc<FutexQueue>>> = Mutex::new(BTreeMap::new());
+
+/// A thread waiting on the queue of `uaddr`, which removes the queue from
+/// [`FUTEX_QUEUES`] when the last waiter leaves.
+struct Waiter {
+    uaddr: usize,
+    queue: Arc<FutexQueue>,
+}
+
+impl Waiter {
+    fn new(uaddr: usize) -> Self {
+        let mut queues = FUTEX_QUEUES.lock();
+        let queue = queues
+            .entry(uaddr)
+            .or_insert_with(|| {
+                Arc::new(FutexQueue {
+                    wq: WaitQueue::new(),
+                    seq: AtomicUsize::new(0),
+                    waiters: AtomicUsize::new(0),
+                })
+            })
+            .clone();
+        queue.waiters.fetch_add(1, Ordering::Relaxed);
+        Self { uaddr, queue }
+    }
+}
+
+impl Drop for Waiter {
+    fn drop(&mut self) {
+        let mut queues = FUTEX_QUEUES.lock();
+        if self.queue.waiters.fetch_sub(1, Ordering::Relaxed) == 1 {
+            queues.remove(&self.uaddr);
+        }
+    }
+}
+
+/// Blocks while the futex word at `uaddr` holds `val`, until it is woken by
+/// [`futex_wake`] or `timeout` elapses.
+///
+/// Fails with `EFAULT` unless `uaddr` is a readable user word, with `EAGAIN`
+/// if the word does not hold `val`, with `ETIMEDOUT` on a timeout, and with
+/// `EINTR` if the thread is killed while waiting. `uaddr` must be aligned.
+pub(crate) fn futex_wait(uaddr: usize, val: u32, timeout: Option<Duration>) -> LinuxResult {
+    let waiter = Waiter::new(uaddr);
+    let queue = &waiter.queue;
+    // Wakers change the word before bumping `seq`, so taking `seq` before
+    // reading the word never misses a wake in between.
+    let seq = queue.seq.load(Ordering::Acquire);
+    {
+        // Keep the word mapped while reading it.
+        let curr = axtask::current();
+        let aspace = curr.task_ext().aspace.lock();
+        crate::syscall::check_user_buf(&aspace, uaddr, core::mem::size_of::<u32>(), false)?;
+        let word = unsafe { &*(uaddr as *const AtomicU32) };
+        if word.load(Ordering::SeqCst) != val {
+            return Err(LinuxError::EAGAIN);
+        }
+    }
+    let woken = || queue.seq.load(Ordering::Acquire) != seq;
+    let done = || woken() || crate::signal::interrupted();
+    let timed_out = match timeout {
+        Some(dur) => queue.wq.wait_timeout_until(dur, done),
+        None => {
+            queue.wq.wait_until(done);
+            false
+        }
+    };
+    if woken() {
+        Ok(())
+    } else if timed_out {
+        Err(LinuxError::ETIMEDOUT)
+    } else {
+        Err(LinuxError::EINTR)
+    }
+}
+
+/// Wakes up to `count` threads waiting on the futex at `uaddr`.
+///
+/// Returns the number of threads woken.
+pub(crate) fn futex_wake(uaddr: usize, count: usize) -> usize {
+    let Some(queue) = FUTEX_QUEUES.lock().get(&uaddr).cloned() else {
+        return 0;
+    };
+    queue.seq.fetch_add(1, Ordering::Release);
+    let mut woken = 0;
+    while woken < count && queue.wq.notify_one(false) {
+        woken += 1;
+    }
+    woken
+}
+
+/// Wakes every futex waiter without changing any word, so that the killed
+/// ones among them see [`crate::signal::interrupted`] and give up.
+pub(crate) fn interrupt_waiters() {
+    let queues: Vec<_> = FUTEX_QUEUES.lock().values().cloned().collect();
+    for queue in queues {
+        queue.wq.notify_all(false);
+    }
+}
@@ -11,20 +11,26 @@ extern crate axlog;
 mod task;
 mod syscall;
 mod loader;
+mod futex;
+mod signal;
 
 use axstd::io;
 use axhal::paging::MappingFlags;
 use axhal::arch::UspaceContext;
 use axhal::mem::VirtAddr;
 use axsync::Mutex;
+use axtask::TaskExtRef;
 use alloc::sync::Arc;
 use alloc::string::String;
 use alloc::collections::BTreeMap;
 use axmm::AddrSpace;
 use loader::load_user_app;
 
+const USER_APP_PATH: &str = "/sbin/mapfile";
 const USER_STACK_SIZE: usize = 0x10000;
 const KERNEL_STACK_SIZE: usize = 0x40000; // 256 KiB
+const USER_HEAP_BASE: usize = 0x4000_0000;
+const USER_HEAP_SIZE: usize = 0x100_0000; // 16 MiB
 
 #[cfg_attr(feature = "axstd", no_mangle)]
 fn main() {
@@ -32,7 +38,7 @@ fn main() {
     let mut uspace = axmm::new_user_aspace().unwrap();
 
     // Load user app binary file into address space.
-    let entry = match load_user_app("/sbin/mapfile", &mut uspace) {
+    let entry = match load_user_app(USER_APP_PATH, &mut uspace) {
         Ok(e) => e,
         Err(err) => panic!("Cannot load app! {:?}", err),
     };
@@ -42,6 +48,9 @@ fn main() {
     let ustack_top = init_user_stack(&mut uspace, true).unwrap();
     ax_println!("New user address space: {:#x?}", uspace);
 
+    // Blocked pipe I/O gives up once its thread is killed.
+    arceos_posix_api::set_interrupt_check(signal::interrupted);
+
     // Let's kick off the user process.
     let user_task = task::spawn_user_task(
         Arc::new(Mutex::new(uspace)),
@@ -50,6 +59,9 @@ fn main() {
 
     // Wait for user process to exit ...
     let exit_code = user_task.join();
+    // ... and for the threads it left behind, which `exit_group` stops.
+    user_task.task_ext().thread_group.wait_empty();
+    ax_println!("all user threads exited");
     ax_println!("monolithic kernel exit [{:?}] normally!", exit_code);
 }
 
@@ -0,0 +1,99 @@
+//! Signal numbers and delivery.
+//!
+//! Handlers are never run. A fatal signal marks its target and wakes it from
+//! blocking waits, and the target terminates itself on its next return to
+//! user space, like the siblings of a thread calling `exit_group`.
+
+use axerrno::{LinuxError, LinuxResult};
+use axtask::{AxTaskRef, TaskExtRef};
+use core::ffi::c_int;
+
+use crate::task::{interrupt, ExitStatus};
+
+pub(crate) const SIGKILL: u32 = 9;
+pub(crate) const SIGTERM: u32 = 15;
+pub(crate) const SIGSTOP: u32 = 19;
+
+/// The number of signals, which are numbered from 1.
+pub(crate) const NSIG: u32 = 64;
+
+/// Take the default action of the signal.
+pub(crate) const SIG_DFL: usize = 0;
+
+/// A set of signals, with bit `n - 1` standing for signal `n`.
+pub(crate) type SigSet = u64;
+
+/// Returns the set containing only `sig`.
+pub(crate) const fn sig_bit(sig: u32) -> SigSet {
+    1 << (sig - 1)
+}
+
+/// Signals whose action and mask cannot be changed.
+pub(crate) const UNCATCHABLE: SigSet = sig_bit(SIGKILL) | sig_bit(SIGSTOP);
+
+/// The action taken on a signal, laid out like `struct sigaction` of the
+/// riscv64 kernel ABI, which has no `sa_restorer`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct SigAction {
+    pub handler: usize,
+    pub flags: usize,
+    pub mask: SigSet,
+}
+
+impl SigAction {
+    pub(crate) const DEFAULT: Self = Self {
+        handler: SIG_DFL,
+        flags: 0,
+        mask: 0,
+    };
+}
+
+/// Checks a signal number from user space, where 0 only probes the target.
+pub(crate) fn check_signal(sig: c_int) -> LinuxResult<u32> {
+    match u32::try_from(sig) {
+        Ok(sig) if sig <= NSIG => Ok(sig),
+        _ => Err(LinuxError::EINVAL),
+    }
+}
+
+/// Checks if `sig` terminates `task`, which is the default for both
+/// `SIGKILL` and `SIGTERM`. Handlers are never run, so a `SIGTERM` with any
+/// other action is dropped.
+fn is_fatal(task: &AxTaskRef, sig: u32) -> bool {
+    match sig {
+        SIGKILL => true,
+        SIGTERM => task.task_ext().thread_group.sig_action(sig).handler == SIG_DFL,
+        _ => false,
+    }
+}
+
+/// Sends `sig` to the single thread `task`.
+pub(crate) fn send_to_thread(task: &AxTaskRef, sig: u32) {
+    if is_fatal(task, sig) {
+        task.task_ext().kill(sig);
+        interrupt(task);
+    } else if sig != 0 {
+        debug!("signal {} to task {} dropped", sig, task.id_name());
+    }
+}
+
+/// Sends `sig` to every thread of the process of `task`.
+pub(crate) fn send_to_process(task: &AxTaskRef, sig: u32) {
+    if is_fatal(task, sig) {
+        let group = &task.task_ext().thread_group;
+        group.start_exit(ExitStatus::Signaled(sig));
+        for thread in group.threads() {
+            interrupt(&thread);
+        }
+    } else if sig != 0 {
+        debug!("signal {} to process {} dropped", sig, task.task_ext().proc_id);
+    }
+}
+
+/// Checks if the current thread has been marked to terminate, so that an
+/// operation blocking on its behalf should be abandoned.
+pub(crate) fn interrupted() -> bool {
+    let curr = axtask::current();
+    curr.task_ext().pending_kill().is_some() || curr.task_ext().thread_group.exiting().is_some()
+}
@@ -1,33 +1,195 @@
 #![allow(dead_code)]
 
 use core::ffi::{c_void, c_char, c_int};
-use axhal::arch::TrapFrame;
-use axhal::trap::{register_trap_handler, SYSCALL};
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+use axhal::arch::{TrapFrame, UspaceContext};
+use axhal::trap::{register_trap_handler, RETURN_TO_USER, SYSCALL};
 use axerrno::{LinuxError, LinuxResult};
 use axtask::current;
 use axtask::TaskExtRef;
 use axhal::paging::MappingFlags;
-use memory_addr::{VirtAddr, VirtAddrRange, PAGE_SIZE_4K};
+use axmm::AddrSpace;
+use memory_addr::{MemoryAddr, VirtAddr, VirtAddrRange, PAGE_SIZE_2M, PAGE_SIZE_4K};
+use alloc::string::String;
+use alloc::vec;
 use alloc::vec::Vec;
 use arceos_posix_api as api;
+use arceos_posix_api::ax_to_linux;
+use crate::signal::{sig_bit, SigAction, SigSet, UNCATCHABLE};
+use crate::task::{ExitStatus, RLimit};
 
+#[cfg(feature = "strace")]
+mod trace;
+
+const SYS_GETCWD: usize = 17;
+const SYS_DUP: usize = 23;
+const SYS_DUP3: usize = 24;
+const SYS_FCNTL: usize = 25;
 const SYS_IOCTL: usize = 29;
+const SYS_MKDIRAT: usize = 34;
+const SYS_UNLINKAT: usize = 35;
+const SYS_STATFS: usize = 43;
+const SYS_FSTATFS: usize = 44;
+const SYS_FTRUNCATE: usize = 46;
+const SYS_FACCESSAT: usize = 48;
+const SYS_CHDIR: usize = 49;
 const SYS_OPENAT: usize = 56;
 const SYS_CLOSE: usize = 57;
+const SYS_PIPE2: usize = 59;
+const SYS_GETDENTS64: usize = 61;
+const SYS_LSEEK: usize = 62;
 const SYS_READ: usize = 63;
 const SYS_WRITE: usize = 64;
+const SYS_READV: usize = 65;
 const SYS_WRITEV: usize = 66;
+const SYS_PREAD64: usize = 67;
+const SYS_PWRITE64: usize = 68;
+const SYS_SENDFILE: usize = 71;
+const SYS_PSELECT6: usize = 72;
+const SYS_PPOLL: usize = 73;
+const SYS_READLINKAT: usize = 78;
+const SYS_FSTAT: usize = 80;
+const SYS_FSYNC: usize = 82;
 const SYS_EXIT: usize = 93;
 const SYS_EXIT_GROUP: usize = 94;
 const SYS_SET_TID_ADDRESS: usize = 96;
+const SYS_FUTEX: usize = 98;
+const SYS_NANOSLEEP: usize = 101;
+const SYS_CLOCK_GETTIME: usize = 113;
+const SYS_CLOCK_GETRES: usize = 114;
+const SYS_CLOCK_NANOSLEEP: usize = 115;
+const SYS_SCHED_YIELD: usize = 124;
+const SYS_TIMES: usize = 153;
+const SYS_KILL: usize = 129;
+const SYS_TKILL: usize = 130;
+const SYS_RT_SIGACTION: usize = 134;
+const SYS_RT_SIGPROCMASK: usize = 135;
+const SYS_UNAME: usize = 160;
+const SYS_UMASK: usize = 166;
+const SYS_GETPID: usize = 172;
+const SYS_GETUID: usize = 174;
+const SYS_GETEUID: usize = 175;
+const SYS_GETGID: usize = 176;
+const SYS_GETEGID: usize = 177;
+const SYS_GETTID: usize = 178;
+const SYS_SYSINFO: usize = 179;
+const SYS_BRK: usize = 214;
+const SYS_MREMAP: usize = 216;
+const SYS_CLONE: usize = 220;
 const SYS_MMAP: usize = 222;
+const SYS_MLOCK: usize = 228;
+const SYS_MUNLOCK: usize = 229;
+const SYS_MINCORE: usize = 232;
+const SYS_MADVISE: usize = 233;
+const SYS_WAIT4: usize = 260;
+const SYS_PRLIMIT64: usize = 261;
+const SYS_RENAMEAT2: usize = 276;
+const SYS_GETRANDOM: usize = 278;
+const SYS_MEMBARRIER: usize = 283;
+
+/// ArceOS-specific: returns how many times the syscall `arg0` was invoked.
+const SYS_SYSCALL_COUNT: usize = 1000;
+/// Dumps the mappings of the process, see [`sys_dump_maps`].
+const SYS_DUMP_MAPS: usize = 1001;
+/// Reports the usage of the kernel allocator, see [`sys_alloc_stats`].
+const SYS_ALLOC_STATS: usize = 1002;
 
 const AT_FDCWD: i32 = -100;
+const AT_REMOVEDIR: i32 = 0x200;
+
+// file type bits of `st_mode`
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+const TCSETS: usize = 0x5402;
+const TCSETSW: usize = 0x5403;
+const TCSETSF: usize = 0x5404;
+const TIOCGWINSZ: usize = 0x5413;
+const FIONREAD: usize = 0x541b;
+const FIONBIO: usize = 0x5421;
+
+const RENAME_NOREPLACE: u32 = 1 << 0;
+
+const F_OK: i32 = 0;
+const X_OK: i32 = 1;
+const W_OK: i32 = 2;
+const R_OK: i32 = 4;
+
+const SEEK_SET: i32 = 0;
+const SEEK_CUR: i32 = 1;
+const SEEK_END: i32 = 2;
+
+const MADV_NORMAL: i32 = 0;
+const MADV_RANDOM: i32 = 1;
+const MADV_SEQUENTIAL: i32 = 2;
+const MADV_WILLNEED: i32 = 3;
+const MADV_DONTNEED: i32 = 4;
+
+const TIMER_ABSTIME: c_int = 1;
+
+// ops of sys_futex
+const FUTEX_WAIT: c_int = 0;
+const FUTEX_WAKE: c_int = 1;
+const FUTEX_PRIVATE_FLAG: c_int = 128;
+
+const GRND_NONBLOCK: u32 = 1 << 0;
+const GRND_RANDOM: u32 = 1 << 1;
+const GRND_INSECURE: u32 = 1 << 2;
+
+// commands of sys_membarrier
+const MEMBARRIER_CMD_QUERY: c_int = 0;
+const MEMBARRIER_CMD_GLOBAL: c_int = 1 << 0;
+const MEMBARRIER_CMD_GLOBAL_EXPEDITED: c_int = 1 << 1;
+const MEMBARRIER_CMD_REGISTER_GLOBAL_EXPEDITED: c_int = 1 << 2;
+const MEMBARRIER_CMD_PRIVATE_EXPEDITED: c_int = 1 << 3;
+const MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED: c_int = 1 << 4;
+/// The commands accepted besides `MEMBARRIER_CMD_QUERY`.
+const MEMBARRIER_SUPPORTED: c_int = MEMBARRIER_CMD_GLOBAL
+    | MEMBARRIER_CMD_GLOBAL_EXPEDITED
+    | MEMBARRIER_CMD_REGISTER_GLOBAL_EXPEDITED
+    | MEMBARRIER_CMD_PRIVATE_EXPEDITED
+    | MEMBARRIER_CMD_REGISTER_PRIVATE_EXPEDITED;
+
+/// The value a syscall returns to user space.
+///
+/// A syscall returns a non-negative value on success, or the negated errno on
+/// failure. This is the only place that encodes it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct SyscallRet(isize);
+
+impl SyscallRet {
+    pub(crate) const fn ok(v: isize) -> Self {
+        Self(v)
+    }
+
+    pub(crate) const fn err(e: LinuxError) -> Self {
+        Self(-(e.code() as isize))
+    }
+
+    pub(crate) const fn into_isize(self) -> isize {
+        self.0
+    }
+}
+
+// The errno convention is checked at build time, as this crate has no test harness.
+const _: () = assert!(SyscallRet::err(LinuxError::EINVAL).into_isize() == -22);
+
+/// Turns the return value of an `arceos_posix_api` call, which is a negated
+/// errno on failure, back into a result for [`syscall_body!`].
+fn api_ret(ret: isize) -> LinuxResult<isize> {
+    if ret < 0 {
+        Err(LinuxError::try_from(-ret as i32).unwrap_or(LinuxError::EINVAL))
+    } else {
+        Ok(ret)
+    }
+}
 
 /// Macro to generate syscall body
 ///
-/// It will receive a function which return Result<_, LinuxError> and convert it to
-/// the type which is specified by the caller.
+/// It will receive a function which return Result<_, LinuxError> and convert it
+/// to a [`SyscallRet`], returned as `isize`.
 #[macro_export]
 macro_rules! syscall_body {
     ($fn: ident, $($stmt: tt)*) => {{
@@ -37,12 +199,12 @@ macro_rules! syscall_body {
             Ok(_) | Err(axerrno::LinuxError::EAGAIN) => debug!(concat!(stringify!($fn), " => {:?}"),  res),
             Err(_) => info!(concat!(stringify!($fn), " => {:?}"), res),
         }
-        match res {
-            Ok(v) => v as _,
-            Err(e) => {
-                -e.code() as _
-            }
-        }
+        #[allow(clippy::unnecessary_cast)]
+        let ret = match res {
+            Ok(v) => $crate::syscall::SyscallRet::ok(v as isize),
+            Err(e) => $crate::syscall::SyscallRet::err(e),
+        };
+        ret.into_isize()
     }};
 }
 
@@ -93,46 +255,614 @@ bitflags::bitflags! {
         const MAP_ANONYMOUS = 1 << 5;
         /// Don't check for reservations.
         const MAP_NORESERVE = 1 << 14;
+        /// Populate (prefault) page tables.
+        const MAP_POPULATE = 0x8000;
         /// Allocation is for a stack.
         const MAP_STACK = 0x20000;
+        /// Create a huge page mapping.
+        const MAP_HUGETLB = 0x40000;
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Debug)]
+    /// flags for sys_mremap
+    ///
+    /// See <https://github.com/bminor/glibc/blob/master/bits/mman-linux.h>
+    struct MremapFlags: i32 {
+        /// The mapping may be moved to a new address.
+        const MREMAP_MAYMOVE = 1 << 0;
+        /// The mapping must be moved to the given new address.
+        const MREMAP_FIXED = 1 << 1;
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Debug, Clone, Copy)]
+    /// flags for sys_clone
+    ///
+    /// See <https://man7.org/linux/man-pages/man2/clone.2.html>
+    struct CloneFlags: u32 {
+        /// Share the address space.
+        const CLONE_VM = 0x100;
+        /// Share the filesystem information (cwd, umask).
+        const CLONE_FS = 0x200;
+        /// Share the file descriptor table.
+        const CLONE_FILES = 0x400;
+        /// Share the signal handlers.
+        const CLONE_SIGHAND = 0x800;
+        /// Put the child into the thread group of the caller.
+        const CLONE_THREAD = 0x10000;
+        /// Share System V semaphore undo values.
+        const CLONE_SYSVSEM = 0x40000;
+        /// Set the TLS of the child.
+        const CLONE_SETTLS = 0x80000;
+        /// Store the child tid in the parent's memory.
+        const CLONE_PARENT_SETTID = 0x100000;
+        /// Clear the child tid in the child's memory and wake a futex on exit.
+        const CLONE_CHILD_CLEARTID = 0x200000;
+        /// Ignored since Linux 2.6.
+        const CLONE_DETACHED = 0x400000;
+        /// Store the child tid in the child's memory.
+        const CLONE_CHILD_SETTID = 0x1000000;
+    }
+}
+
+/// The low byte of the clone flags holds the signal sent to the parent on exit.
+const CSIGNAL: usize = 0xff;
+
+// options for sys_wait4
+const WNOHANG: c_int = 1;
+const WUNTRACED: c_int = 2;
+const WCONTINUED: c_int = 8;
+const __WNOTHREAD: c_int = 0x2000_0000;
+const __WALL: c_int = 0x4000_0000;
+const __WCLONE: c_int = 0x8000_0000_u32 as c_int;
+
+// how for sys_rt_sigprocmask
+const SIG_BLOCK: c_int = 0;
+const SIG_UNBLOCK: c_int = 1;
+const SIG_SETMASK: c_int = 2;
+
+/// Size of `struct rusage` of sys_wait4.
+const RUSAGE_SIZE: usize = 144;
+
+// events for sys_ppoll
+const POLLIN: i16 = 0x001;
+const POLLOUT: i16 = 0x004;
+const POLLNVAL: i16 = 0x020;
+
+/// Checks that the user buffer `[ptr, ptr + len)` is mapped in `aspace` with
+/// user access, and writable too if the kernel is going to `write` into it.
+pub(crate) fn check_user_buf(aspace: &AddrSpace, ptr: usize, len: usize, write: bool) -> LinuxResult {
+    if len == 0 {
+        return Ok(());
+    }
+    if ptr == 0 {
+        return Err(LinuxError::EFAULT);
+    }
+    let end = ptr.checked_add(len).ok_or(LinuxError::EFAULT)?;
+    let start = VirtAddr::from(ptr).align_down_4k();
+    let end = VirtAddr::from(end).align_up_4k();
+    let access = if write {
+        MappingFlags::USER | MappingFlags::WRITE
+    } else {
+        MappingFlags::USER | MappingFlags::READ
+    };
+    if !aspace.can_access_range(start, end - start, access) {
+        return Err(LinuxError::EFAULT);
+    }
+    Ok(())
+}
+
+/// Copies `val` to the user address `uptr` through the page table of `aspace`.
+///
+/// Fails with `EFAULT` unless the whole destination is writable by the user.
+pub(crate) fn copy_to_user<T: ?Sized>(aspace: &AddrSpace, uptr: usize, val: &T) -> LinuxResult {
+    let size = core::mem::size_of_val(val);
+    check_user_buf(aspace, uptr, size, true)?;
+    let bytes = unsafe { core::slice::from_raw_parts(val as *const T as *const u8, size) };
+    aspace
+        .write(VirtAddr::from(uptr), bytes)
+        .map_err(|_| LinuxError::EFAULT)
+}
+
+/// Copies a `T` from the user address `uptr` through the page table of `aspace`.
+///
+/// `T` must be plain data that is valid for any bit pattern. Fails with
+/// `EFAULT` unless the whole source is readable by the user.
+pub(crate) fn copy_from_user<T: Copy>(aspace: &AddrSpace, uptr: usize) -> LinuxResult<T> {
+    let size = core::mem::size_of::<T>();
+    check_user_buf(aspace, uptr, size, false)?;
+    let mut val = core::mem::MaybeUninit::<T>::uninit();
+    let bytes = unsafe { core::slice::from_raw_parts_mut(val.as_mut_ptr() as *mut u8, size) };
+    aspace
+        .read(VirtAddr::from(uptr), bytes)
+        .map_err(|_| LinuxError::EFAULT)?;
+    Ok(unsafe { val.assume_init() })
+}
+
+/// Copies `len` consecutive `T`s from the user address `uptr`, like
+/// [`copy_from_user`] does for one.
+pub(crate) fn copy_slice_from_user<T: Copy>(
+    aspace: &AddrSpace,
+    uptr: usize,
+    len: usize,
+) -> LinuxResult<Vec<T>> {
+    let size = core::mem::size_of::<T>()
+        .checked_mul(len)
+        .ok_or(LinuxError::EFAULT)?;
+    check_user_buf(aspace, uptr, size, false)?;
+    let mut vals = Vec::<T>::with_capacity(len);
+    if size > 0 {
+        let bytes = unsafe { core::slice::from_raw_parts_mut(vals.as_mut_ptr() as *mut u8, size) };
+        aspace
+            .read(VirtAddr::from(uptr), bytes)
+            .map_err(|_| LinuxError::EFAULT)?;
+    }
+    unsafe { vals.set_len(len) };
+    Ok(vals)
+}
+
+/// The longest path accepted from user space, including the NUL.
+const PATH_MAX: usize = 4096;
+
+/// Copies a NUL-terminated string of at most [`PATH_MAX`] bytes from user
+/// space, checking each page before reading from it.
+///
+/// Fails with `EFAULT` if the string runs into memory the user cannot read,
+/// `ENAMETOOLONG` if there is no NUL within `PATH_MAX` bytes, and `EINVAL` if
+/// it is not UTF-8.
+fn user_str(ptr: *const c_char) -> LinuxResult<String> {
+    let curr = current();
+    let aspace = curr.task_ext().aspace.lock();
+    let mut addr = ptr as usize;
+    let mut bytes = Vec::new();
+    while bytes.len() < PATH_MAX {
+        let page_end = VirtAddr::from(addr).align_down_4k().as_usize() + PAGE_SIZE_4K;
+        let len = (page_end - addr).min(PATH_MAX - bytes.len());
+        check_user_buf(&aspace, addr, len, false)?;
+        let start = bytes.len();
+        bytes.resize(start + len, 0);
+        aspace
+            .read(VirtAddr::from(addr), &mut bytes[start..])
+            .map_err(|_| LinuxError::EFAULT)?;
+        if let Some(nul) = bytes[start..].iter().position(|&b| b == 0) {
+            bytes.truncate(start + nul);
+            return String::from_utf8(bytes).map_err(|_| LinuxError::EINVAL);
+        }
+        addr = page_end;
+    }
+    Err(LinuxError::ENAMETOOLONG)
+}
+
+/// Resolves `path` relative to the directory referred to by `dirfd`.
+///
+/// Absolute paths and `AT_FDCWD` leave `path` as it is, to be looked up from
+/// the working directory. Otherwise `dirfd` must be an open directory, or it
+/// fails with `EBADF` or `ENOTDIR`.
+fn resolve_at(dirfd: c_int, path: String) -> LinuxResult<String> {
+    if path.starts_with('/') || dirfd == AT_FDCWD {
+        return Ok(path);
+    }
+    let dir = api::get_file_like(dirfd)?;
+    let is_dir = dir.stat()?.st_mode & S_IFMT == S_IFDIR;
+    match dir.path() {
+        Some(base) if is_dir => {
+            let base = base.trim_end_matches('/');
+            Ok(alloc::format!("{}/{}", base, path))
+        }
+        _ => Err(LinuxError::ENOTDIR),
+    }
+}
+
+/// File status in the layout of the generic Linux `struct stat`
+///
+/// It differs from [`api::ctypes::stat`], which follows the axlibc layout.
+///
+/// See <https://github.com/torvalds/linux/blob/master/include/uapi/asm-generic/stat.h>
+#[repr(C)]
+#[derive(Default)]
+struct KernelStat {
+    st_dev: u64,
+    st_ino: u64,
+    st_mode: u32,
+    st_nlink: u32,
+    st_uid: u32,
+    st_gid: u32,
+    st_rdev: u64,
+    __pad1: u64,
+    st_size: i64,
+    st_blksize: i32,
+    __pad2: i32,
+    st_blocks: i64,
+    st_atime_sec: i64,
+    st_atime_nsec: i64,
+    st_mtime_sec: i64,
+    st_mtime_nsec: i64,
+    st_ctime_sec: i64,
+    st_ctime_nsec: i64,
+    __unused: [u32; 2],
+}
+
+impl From<api::ctypes::stat> for KernelStat {
+    fn from(st: api::ctypes::stat) -> Self {
+        Self {
+            st_dev: st.st_dev as _,
+            st_ino: st.st_ino as _,
+            st_mode: st.st_mode as _,
+            st_nlink: st.st_nlink as _,
+            st_uid: st.st_uid as _,
+            st_gid: st.st_gid as _,
+            st_rdev: st.st_rdev as _,
+            st_size: st.st_size as _,
+            st_blksize: st.st_blksize as _,
+            st_blocks: st.st_blocks as _,
+            st_atime_sec: st.st_atime.tv_sec as _,
+            st_atime_nsec: st.st_atime.tv_nsec as _,
+            st_mtime_sec: st.st_mtime.tv_sec as _,
+            st_mtime_nsec: st.st_mtime.tv_nsec as _,
+            st_ctime_sec: st.st_ctime.tv_sec as _,
+            st_ctime_nsec: st.st_ctime.tv_nsec as _,
+            ..Default::default()
+        }
+    }
+}
+
+/// Filesystem statistics returned by sys_statfs
+///
+/// See <https://man7.org/linux/man-pages/man2/statfs.2.html>
+#[repr(C)]
+#[derive(Default)]
+struct StatFs {
+    f_type: isize,
+    f_bsize: isize,
+    f_blocks: u64,
+    f_bfree: u64,
+    f_bavail: u64,
+    f_files: u64,
+    f_ffree: u64,
+    f_fsid: [i32; 2],
+    f_namelen: isize,
+    f_frsize: isize,
+    f_flags: isize,
+    f_spare: [isize; 4],
+}
+
+impl From<axfs::api::FileSystemStat> for StatFs {
+    fn from(stat: axfs::api::FileSystemStat) -> Self {
+        Self {
+            f_type: stat.magic as _,
+            f_bsize: stat.block_size as _,
+            f_blocks: stat.blocks,
+            f_bfree: stat.blocks_free,
+            f_bavail: stat.blocks_free,
+            f_namelen: stat.name_max as _,
+            f_frsize: stat.block_size as _,
+            ..Default::default()
+        }
+    }
+}
+
+/// Terminal window size returned by `TIOCGWINSZ`
+#[repr(C)]
+struct WinSize {
+    ws_row: u16,
+    ws_col: u16,
+    ws_xpixel: u16,
+    ws_ypixel: u16,
+}
+
+impl WinSize {
+    /// The serial console has no size of its own, so report the classic 24x80.
+    const CONSOLE: Self = Self {
+        ws_row: 24,
+        ws_col: 80,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+}
+
+/// An entry of the fd array of sys_ppoll
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PollFd {
+    fd: c_int,
+    events: i16,
+    revents: i16,
+}
+
+/// Clock ticks per second as seen by user space (`sysconf(_SC_CLK_TCK)`).
+const USER_HZ: u64 = 100;
+
+/// Process times returned by sys_times, in `USER_HZ` ticks
+///
+/// See <https://man7.org/linux/man-pages/man2/times.2.html>
+#[repr(C)]
+struct Tms {
+    tms_utime: isize,
+    tms_stime: isize,
+    tms_cutime: isize,
+    tms_cstime: isize,
+}
+
+/// System statistics returned by sys_sysinfo
+///
+/// See <https://man7.org/linux/man-pages/man2/sysinfo.2.html>
+#[repr(C)]
+#[derive(Default)]
+struct SysInfo {
+    uptime: isize,
+    loads: [usize; 3],
+    totalram: usize,
+    freeram: usize,
+    sharedram: usize,
+    bufferram: usize,
+    totalswap: usize,
+    freeswap: usize,
+    procs: u16,
+    pad: u16,
+    totalhigh: usize,
+    freehigh: usize,
+    mem_unit: u32,
+}
+
+/// Memory usage of the global allocator returned by [`sys_alloc_stats`].
+#[repr(C)]
+struct AllocStats {
+    used: u64,
+    free: u64,
+    peak: u64,
+}
+
+const UTSNAME_LEN: usize = 65;
+
+/// System information returned by sys_uname
+///
+/// See <https://man7.org/linux/man-pages/man2/uname.2.html>
+#[repr(C)]
+struct UtsName {
+    sysname: [u8; UTSNAME_LEN],
+    nodename: [u8; UTSNAME_LEN],
+    release: [u8; UTSNAME_LEN],
+    version: [u8; UTSNAME_LEN],
+    machine: [u8; UTSNAME_LEN],
+    domainname: [u8; UTSNAME_LEN],
+}
+
+impl UtsName {
+    /// Copies `s` into a NUL-terminated field, truncating it if needed.
+    fn field(s: &str) -> [u8; UTSNAME_LEN] {
+        let mut buf = [0u8; UTSNAME_LEN];
+        let len = s.len().min(UTSNAME_LEN - 1);
+        buf[..len].copy_from_slice(&s.as_bytes()[..len]);
+        buf
+    }
+}
+
+/// The handler of a syscall, which decodes its arguments from the trap frame.
+type SyscallHandler = fn(&TrapFrame) -> isize;
+
+/// All handled syscalls. Registering a new syscall only takes an entry here.
+const SYSCALLS: &[(usize, SyscallHandler)] = &[
+    (SYS_GETCWD, |tf| sys_getcwd(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_DUP, |tf| sys_dup(tf.arg0() as _)),
+    (SYS_DUP3, |tf| sys_dup3(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_FCNTL, |tf| sys_fcntl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_IOCTL, |tf| sys_ioctl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_MKDIRAT, |tf| sys_mkdirat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_UNLINKAT, |tf| sys_unlinkat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_SET_TID_ADDRESS, |tf| sys_set_tid_address(tf.arg0() as _)),
+    (SYS_FUTEX, |tf| sys_futex(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)),
+    (SYS_STATFS, |tf| sys_statfs(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_FSTATFS, |tf| sys_fstatfs(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_FTRUNCATE, |tf| sys_ftruncate(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_FACCESSAT, |tf| sys_faccessat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_CHDIR, |tf| sys_chdir(tf.arg0() as _)),
+    (SYS_OPENAT, |tf| sys_openat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)),
+    (SYS_CLOSE, |tf| sys_close(tf.arg0() as _)),
+    (SYS_PIPE2, |tf| sys_pipe2(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_GETDENTS64, |tf| sys_getdents64(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_LSEEK, |tf| sys_lseek(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_READ, |tf| sys_read(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_WRITE, |tf| sys_write(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_READV, |tf| sys_readv(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_WRITEV, |tf| sys_writev(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_PREAD64, |tf| sys_pread64(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)),
+    (SYS_PWRITE64, |tf| sys_pwrite64(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)),
+    (SYS_SENDFILE, |tf| sys_sendfile(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)),
+    (SYS_PSELECT6, |tf| sys_pselect6(
+        tf.arg0() as _,
+        tf.arg1() as _,
+        tf.arg2() as _,
+        tf.arg3() as _,
+        tf.arg4() as _,
+        tf.arg5() as _,
+    )),
+    (SYS_PPOLL, |tf| {
+        sys_ppoll(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _, tf.arg4() as _)
+    }),
+    (SYS_READLINKAT, |tf| sys_readlinkat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)),
+    (SYS_FSTAT, |tf| sys_fstat(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_FSYNC, |tf| sys_fsync(tf.arg0() as _)),
+    (SYS_EXIT_GROUP, |tf| {
+        ax_println!("[SYS_EXIT_GROUP]: system is exiting ..");
+        crate::task::exit_group(tf.arg0() as _)
+    }),
+    (SYS_EXIT, |tf| {
+        ax_println!("[SYS_EXIT]: system is exiting ..");
+        crate::task::exit_thread(tf.arg0() as _)
+    }),
+    (SYS_MMAP, |tf| sys_mmap(
+        tf.arg0() as _,
+        tf.arg1() as _,
+        tf.arg2() as _,
+        tf.arg3() as _,
+        tf.arg4() as _,
+        tf.arg5() as _,
+    )),
+    (SYS_NANOSLEEP, |tf| sys_nanosleep(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_CLOCK_GETTIME, |tf| sys_clock_gettime(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_CLOCK_GETRES, |tf| sys_clock_getres(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_CLOCK_NANOSLEEP, |tf| {
+        sys_clock_nanosleep(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)
+    }),
+    (SYS_SCHED_YIELD, |_| sys_sched_yield()),
+    (SYS_TIMES, |tf| sys_times(tf.arg0() as _)),
+    (SYS_KILL, |tf| sys_kill(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_TKILL, |tf| sys_tkill(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_RT_SIGACTION, |tf| {
+        sys_rt_sigaction(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)
+    }),
+    (SYS_RT_SIGPROCMASK, |tf| {
+        sys_rt_sigprocmask(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)
+    }),
+    (SYS_UNAME, |tf| sys_uname(tf.arg0() as _)),
+    (SYS_UMASK, |tf| sys_umask(tf.arg0() as _)),
+    (SYS_GETPID, |_| sys_getpid()),
+    (SYS_GETUID, |_| sys_getuid()),
+    (SYS_GETEUID, |_| sys_geteuid()),
+    (SYS_GETGID, |_| sys_getgid()),
+    (SYS_GETEGID, |_| sys_getegid()),
+    (SYS_GETTID, |_| sys_gettid()),
+    (SYS_SYSINFO, |tf| sys_sysinfo(tf.arg0() as _)),
+    (SYS_BRK, |tf| sys_brk(tf.arg0() as _)),
+    (SYS_CLONE, |tf| sys_clone(
+        tf,
+        tf.arg0() as _,
+        tf.arg1() as _,
+        tf.arg2() as _,
+        tf.arg3() as _,
+        tf.arg4() as _,
+    )),
+    (SYS_MREMAP, |tf| sys_mremap(
+        tf.arg0() as _,
+        tf.arg1() as _,
+        tf.arg2() as _,
+        tf.arg3() as _,
+        tf.arg4() as _,
+    )),
+    (SYS_MLOCK, |tf| sys_mlock(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_MUNLOCK, |tf| sys_munlock(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_MINCORE, |tf| sys_mincore(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_MADVISE, |tf| sys_madvise(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_WAIT4, |tf| {
+        sys_wait4(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)
+    }),
+    (SYS_PRLIMIT64, |tf| {
+        sys_prlimit64(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _)
+    }),
+    (SYS_RENAMEAT2, |tf| sys_renameat2(
+        tf.arg0() as _,
+        tf.arg1() as _,
+        tf.arg2() as _,
+        tf.arg3() as _,
+        tf.arg4() as _,
+    )),
+    (SYS_GETRANDOM, |tf| sys_getrandom(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_MEMBARRIER, |tf| sys_membarrier(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _)),
+    (SYS_SYSCALL_COUNT, |tf| sys_syscall_count(tf.arg0())),
+    (SYS_DUMP_MAPS, |tf| sys_dump_maps(tf.arg0() as _, tf.arg1() as _)),
+    (SYS_ALLOC_STATS, |tf| sys_alloc_stats(tf.arg0() as _)),
+];
+
+/// One more than the largest syscall number handled here.
+const NR_SYSCALLS: usize = max_syscall_num(SYSCALLS) + 1;
+
+/// Syscall handlers, indexed by syscall number.
+static SYSCALL_TABLE: [Option<SyscallHandler>; NR_SYSCALLS] = build_syscall_table(SYSCALLS);
+
+/// Invocation counters, indexed by syscall number.
+static SYSCALL_COUNTS: [AtomicUsize; NR_SYSCALLS] = [const { AtomicUsize::new(0) }; NR_SYSCALLS];
+
+const fn max_syscall_num(entries: &[(usize, SyscallHandler)]) -> usize {
+    let mut max = 0;
+    let mut i = 0;
+    while i < entries.len() {
+        if entries[i].0 > max {
+            max = entries[i].0;
+        }
+        i += 1;
     }
+    max
+}
+
+const fn build_syscall_table(
+    entries: &[(usize, SyscallHandler)],
+) -> [Option<SyscallHandler>; NR_SYSCALLS] {
+    let mut table = [None; NR_SYSCALLS];
+    let mut i = 0;
+    while i < entries.len() {
+        let (num, handler) = entries[i];
+        assert!(table[num].is_none(), "duplicate syscall number");
+        table[num] = Some(handler);
+        i += 1;
+    }
+    table
 }
 
 #[register_trap_handler(SYSCALL)]
 fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
     ax_println!("handle_syscall [{}] ...", syscall_num);
-    let ret = match syscall_num {
-         SYS_IOCTL => sys_ioctl(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _) as _,
-        SYS_SET_TID_ADDRESS => sys_set_tid_address(tf.arg0() as _),
-        SYS_OPENAT => sys_openat(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _, tf.arg3() as _),
-        SYS_CLOSE => sys_close(tf.arg0() as _),
-        SYS_READ => sys_read(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
-        SYS_WRITE => sys_write(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
-        SYS_WRITEV => sys_writev(tf.arg0() as _, tf.arg1() as _, tf.arg2() as _),
-        SYS_EXIT_GROUP => {
-            ax_println!("[SYS_EXIT_GROUP]: system is exiting ..");
-            axtask::exit(tf.arg0() as _)
-        },
-        SYS_EXIT => {
-            ax_println!("[SYS_EXIT]: system is exiting ..");
-            axtask::exit(tf.arg0() as _)
-        },
-        SYS_MMAP => sys_mmap(
-            tf.arg0() as _,
-            tf.arg1() as _,
-            tf.arg2() as _,
-            tf.arg3() as _,
-            tf.arg4() as _,
-            tf.arg5() as _,
-        ),
-        _ => {
+    exit_if_killed();
+    current().task_ext().enter_kernel();
+    if let Some(count) = SYSCALL_COUNTS.get(syscall_num) {
+        count.fetch_add(1, Ordering::Relaxed);
+    }
+    #[cfg(feature = "strace")]
+    let call = trace::trace_syscall(
+        syscall_num,
+        &[tf.arg0(), tf.arg1(), tf.arg2(), tf.arg3(), tf.arg4(), tf.arg5()],
+    );
+    let ret = match SYSCALL_TABLE.get(syscall_num).copied().flatten() {
+        Some(handler) => handler(tf),
+        None => {
             ax_println!("Unimplemented syscall: {}", syscall_num);
-            -LinuxError::ENOSYS.code() as _
+            SyscallRet::err(LinuxError::ENOSYS).into_isize()
         }
     };
+    #[cfg(feature = "strace")]
+    ax_println!("{} = {}", call, ret);
+    current().task_ext().leave_kernel();
     ret
 }
 
+/// Stops a killed thread on its way back to user space, whether it trapped
+/// for a syscall, a page fault or a timer interrupt, so that one spinning in
+/// user space cannot outlive its process.
+#[register_trap_handler(RETURN_TO_USER)]
+fn return_to_user(_tf: &TrapFrame) {
+    exit_if_killed();
+}
+
+/// Returns how many times each syscall has been invoked, indexed by number.
+pub(crate) fn syscall_counts() -> [usize; NR_SYSCALLS] {
+    core::array::from_fn(syscall_count)
+}
+
+/// Returns how many times the syscall `num` has been invoked.
+fn sys_syscall_count(num: usize) -> isize {
+    syscall_body!(sys_syscall_count, Ok(syscall_count(num)))
+}
+
+fn syscall_count(num: usize) -> usize {
+    SYSCALL_COUNTS
+        .get(num)
+        .map_or(0, |count| count.load(Ordering::Relaxed))
+}
+
+/// Makes the current thread follow an `exit_group` issued by a sibling, or a
+/// fatal signal sent to it or its process.
+fn exit_if_killed() {
+    let curr = current();
+    if let Some(status) = curr.task_ext().thread_group.exiting() {
+        crate::task::terminate(status);
+    }
+    if let Some(sig) = curr.task_ext().pending_kill() {
+        crate::task::terminate(ExitStatus::Signaled(sig));
+    }
+}
+
 fn sys_mmap(
     addr: usize,
     length: usize,
@@ -149,10 +879,21 @@ fn sys_mmap(
             .ok_or(LinuxError::EINVAL)?;
         
         // 对齐长度到 4KB
-        let aligned_length = (length + PAGE_SIZE_4K - 1) & !(PAGE_SIZE_4K - 1);
+        // Like Linux, a range that wraps once rounded up to pages is invalid.
+        let aligned_length = length
+            .checked_add(PAGE_SIZE_4K - 1)
+            .filter(|end| addr.checked_add(*end).is_some())
+            .ok_or(LinuxError::EINVAL)?
+            & !(PAGE_SIZE_4K - 1);
         if aligned_length == 0 {
             return Err(LinuxError::EINVAL);
         }
+
+        // 大页映射：长度和地址必须按 2MB 对齐
+        let hugetlb = mmap_flags.contains(MmapFlags::MAP_HUGETLB);
+        if hugetlb && (length % PAGE_SIZE_2M != 0 || addr % PAGE_SIZE_2M != 0) {
+            return Err(LinuxError::EINVAL);
+        }
         
         // 获取地址空间
         let curr = current();
@@ -173,96 +914,1559 @@ fn sys_mmap(
                 aspace.base(),
                 aspace.size()
             );
-            aspace.find_free_area(hint, aligned_length, limit)
-                .ok_or(LinuxError::ENOMEM)?
+            if hugetlb {
+                // 多找一个大页的空间，再把起始地址对齐到 2MB
+                aspace.find_free_area(hint, aligned_length + PAGE_SIZE_2M - PAGE_SIZE_4K, limit)
+                    .ok_or(LinuxError::ENOMEM)?
+                    .align_up(PAGE_SIZE_2M)
+            } else {
+                aspace.find_free_area(hint, aligned_length, limit)
+                    .ok_or(LinuxError::ENOMEM)?
+            }
         };
         
         // 转换权限标志
         let mapping_flags = MappingFlags::from(mmap_prot);
+
+        // 用户页错误尚未处理，所以无论是否有 MAP_POPULATE，映射都立即分配全部页面
+        let populate = true;
+        // 地址空间还不支持 2MB 页，大页映射退回到按 4KB 页映射同一块对齐的区域
+        if hugetlb {
+            debug!("sys_mmap: MAP_HUGETLB falls back to 4K pages at {:#x}", start_addr);
+        }
         
         // 处理文件映射或匿名映射
         if mmap_flags.contains(MmapFlags::MAP_ANONYMOUS) {
             // 匿名映射：直接分配内存
-            aspace.map_alloc(start_addr, aligned_length, mapping_flags, true)
-                .map_err(|e| match e {
-                    axerrno::AxError::NoMemory => LinuxError::ENOMEM,
-                    axerrno::AxError::InvalidInput => LinuxError::EINVAL,
-                    _ => LinuxError::EAGAIN,
-                })?;
+            aspace.map_alloc(start_addr, aligned_length, mapping_flags, populate)
+                .map_err(ax_to_linux)?;
         } else {
             // 文件映射：需要从文件读取内容
             if fd < 0 {
                 return Err(LinuxError::EBADF);
             }
-            
-            // 获取文件对象
-            let file_like = api::imp::fd_ops::get_file_like(fd)?;
-            
-            // 分配内存
-            aspace.map_alloc(start_addr, aligned_length, mapping_flags, true)
-                .map_err(|e| match e {
-                    axerrno::AxError::NoMemory => LinuxError::ENOMEM,
-                    axerrno::AxError::InvalidInput => LinuxError::EINVAL,
-                    _ => LinuxError::EAGAIN,
-                })?;
-            
-            // 先 seek 到 offset 位置（即使 offset 为 0，也要确保从文件开头读取）
-            let seek_result = api::sys_lseek(fd, offset, 0); // SEEK_SET = 0
-            if seek_result < 0 {
+            if offset < 0 {
                 return Err(LinuxError::EINVAL);
             }
             
-            // 读取文件内容到临时缓冲区
-            let mut file_data = vec![0u8; length];
-            let mut total_read = 0;
-            
-            // 读取文件内容
-            while total_read < length {
-                let buf = &mut file_data[total_read..];
-                let read_size = file_like.read(buf)?;
-                if read_size == 0 {
-                    break; // EOF
-                }
-                total_read += read_size;
+            // 只有普通文件可以映射
+            let file = api::get_file_like(fd)?;
+            if file.stat()?.st_mode & S_IFMT != S_IFREG {
+                return Err(LinuxError::ENODEV);
             }
             
-            // 将文件内容写入映射的内存
-            aspace.write(start_addr, &file_data[..total_read])
-                .map_err(|_| LinuxError::EFAULT)?;
+            // 分配内存
+            aspace.map_alloc(start_addr, aligned_length, mapping_flags, populate)
+                .map_err(ax_to_linux)?;
+
+            // 读取失败时撤销映射，不留下半初始化的区域
+            if let Err(e) = fill_from_file(&aspace, start_addr, length, fd, offset) {
+                let _ = aspace.unmap(start_addr, aligned_length);
+                return Err(e);
+            }
         }
+
+        curr.task_ext().thread_group.add_mapping(crate::task::MappingRecord {
+            start: start_addr.as_usize(),
+            fd: if mmap_flags.contains(MmapFlags::MAP_ANONYMOUS) { -1 } else { fd },
+            offset: if mmap_flags.contains(MmapFlags::MAP_ANONYMOUS) { 0 } else { offset as usize },
+            shared: mmap_flags.contains(MmapFlags::MAP_SHARED),
+        });
         
         Ok(start_addr.as_usize() as isize)
     })
 }
 
-fn sys_openat(dfd: c_int, fname: *const c_char, flags: c_int, mode: api::ctypes::mode_t) -> isize {
-    assert_eq!(dfd, AT_FDCWD);
-    api::sys_open(fname, flags, mode) as isize
+/// Fills the mapping at `start` with `length` bytes of `fd` from `offset` on.
+///
+/// It reads with pread, so the file position is left unchanged. Pages past
+/// the end of the file stay zeroed.
+fn fill_from_file(
+    aspace: &AddrSpace,
+    start: VirtAddr,
+    length: usize,
+    fd: i32,
+    offset: isize,
+) -> LinuxResult {
+    // 读取文件内容到临时缓冲区
+    let mut file_data = vec![0u8; length];
+    let mut total_read = 0;
+    while total_read < length {
+        let buf = &mut file_data[total_read..];
+        let read_size = api_ret(api::sys_pread(
+            fd,
+            buf.as_mut_ptr() as _,
+            buf.len(),
+            (offset + total_read as isize) as _,
+        ))?;
+        if read_size == 0 {
+            break; // EOF
+        }
+        total_read += read_size as usize;
+    }
+
+    // 将文件内容写入映射的内存；映射可能对用户只读，所以直接经页表写入
+    aspace
+        .write(start, &file_data[..total_read])
+        .map_err(|_| LinuxError::EFAULT)
 }
 
-fn sys_close(fd: i32) -> isize {
-    api::sys_close(fd) as isize
+/// Sets the program break of the current task.
+///
+/// Like Linux, it never fails: the current break is returned if the request
+/// is out of the heap range or the pages cannot be mapped.
+fn sys_brk(addr: usize) -> isize {
+    syscall_body!(sys_brk, {
+        let curr = current();
+        let task_ext = curr.task_ext();
+        let old_top = task_ext.thread_group.heap_top() as usize;
+        if addr < crate::USER_HEAP_BASE || addr > crate::USER_HEAP_BASE + crate::USER_HEAP_SIZE {
+            return Ok(old_top as isize);
+        }
+
+        let old_end = (old_top + PAGE_SIZE_4K - 1) & !(PAGE_SIZE_4K - 1);
+        let new_end = (addr + PAGE_SIZE_4K - 1) & !(PAGE_SIZE_4K - 1);
+        let mut aspace = task_ext.aspace.lock();
+        if new_end > old_end {
+            let flags = MappingFlags::READ | MappingFlags::WRITE | MappingFlags::USER;
+            if aspace
+                .map_alloc(VirtAddr::from(old_end), new_end - old_end, flags, true)
+                .is_err()
+            {
+                return Ok(old_top as isize);
+            }
+        } else if new_end < old_end {
+            aspace.unmap(VirtAddr::from(new_end), old_end - new_end).map_err(ax_to_linux)?;
+        }
+
+        task_ext.thread_group.set_heap_top(addr as u64);
+        Ok(addr as isize)
+    })
 }
 
-fn sys_read(fd: i32, buf: *mut c_void, count: usize) -> isize {
-    api::sys_read(fd, buf, count)
+/// Creates a thread sharing the address space of the caller.
+///
+/// Only the flag combination used by pthreads is supported, creating new
+/// processes is not. The fd table is global and thus always shared, and the
+/// heap, cwd and umask belong to the thread group shared with the new thread.
+fn sys_clone(
+    tf: &TrapFrame,
+    flags: usize,
+    newsp: usize,
+    ptid: *mut u32,
+    tls: usize,
+    ctid: *mut u32,
+) -> isize {
+    syscall_body!(sys_clone, {
+        let flags = CloneFlags::from_bits((flags & !CSIGNAL) as u32).ok_or_else(|| {
+            warn!("sys_clone: unsupported flags {:#x}", flags);
+            LinuxError::EINVAL
+        })?;
+        let required = CloneFlags::CLONE_VM
+            | CloneFlags::CLONE_FS
+            | CloneFlags::CLONE_FILES
+            | CloneFlags::CLONE_SIGHAND
+            | CloneFlags::CLONE_THREAD;
+        if !flags.contains(required) {
+            warn!("sys_clone: only threads can be created, got {:?}", flags);
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        {
+            let aspace = curr.task_ext().aspace.lock();
+            if flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+                check_user_buf(&aspace, ptid as usize, core::mem::size_of::<u32>(), true)?;
+            }
+            if flags.intersects(CloneFlags::CLONE_CHILD_SETTID | CloneFlags::CLONE_CHILD_CLEARTID) {
+                check_user_buf(&aspace, ctid as usize, core::mem::size_of::<u32>(), true)?;
+            }
+        }
+
+        let mut uctx = UspaceContext::from(tf);
+        // Resume after the `ecall`, as the trap handler does for the parent.
+        uctx.set_ip(uctx.get_ip() + 4);
+        uctx.set_retval(0);
+        if newsp != 0 {
+            uctx.set_sp(newsp);
+        }
+        if flags.contains(CloneFlags::CLONE_SETTLS) {
+            uctx.set_tls(tls);
+        }
+
+        let parent = curr.task_ext();
+        let ext = crate::task::TaskExt::new(uctx, parent.aspace.clone(), parent.thread_group.clone())
+            .with_parent(parent.children.clone());
+        ext.set_sig_mask(parent.sig_mask());
+        if flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) {
+            ext.set_clear_child_tid(ctid as u64);
+        }
+        let task = crate::task::new_user_task(ext, "user_thread".into());
+        let tid = task.id().as_u64();
+        parent.children.add(tid);
+        // The tid must be visible before either thread returns to user space.
+        {
+            let aspace = parent.aspace.lock();
+            if flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+                copy_to_user(&aspace, ptid as usize, &(tid as u32))?;
+            }
+            if flags.contains(CloneFlags::CLONE_CHILD_SETTID) {
+                copy_to_user(&aspace, ctid as usize, &(tid as u32))?;
+            }
+        }
+        crate::task::spawn_user_thread(task);
+        Ok(tid as isize)
+    })
 }
 
-fn sys_write(fd: i32, buf: *const c_void, count: usize) -> isize {
-    api::sys_write(fd, buf, count)
+/// Waits for a child created by [`sys_clone`] to exit.
+///
+/// There are no process groups, so any `pid` that is not positive waits
+/// for any child. Resource usage is not tracked and reported as zero.
+fn sys_wait4(pid: i32, wstatus: *mut c_int, options: c_int, rusage: *mut u8) -> isize {
+    syscall_body!(sys_wait4, {
+        if options & !(WNOHANG | WUNTRACED | WCONTINUED | __WNOTHREAD | __WALL | __WCLONE) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        {
+            let aspace = curr.task_ext().aspace.lock();
+            if !wstatus.is_null() {
+                check_user_buf(&aspace, wstatus as usize, core::mem::size_of::<c_int>(), true)?;
+            }
+            if !rusage.is_null() {
+                check_user_buf(&aspace, rusage as usize, RUSAGE_SIZE, true)?;
+            }
+        }
+        let id = (pid > 0).then_some(pid as u64);
+        let children = curr.task_ext().children.clone();
+        let Some((tid, status)) = children.wait(id, options & WNOHANG != 0)? else {
+            return Ok(0);
+        };
+        let aspace = curr.task_ext().aspace.lock();
+        if !wstatus.is_null() {
+            copy_to_user(&aspace, wstatus as usize, &status.wait_status())?;
+        }
+        if !rusage.is_null() {
+            copy_to_user(&aspace, rusage as usize, &[0u8; RUSAGE_SIZE])?;
+        }
+        Ok(tid as isize)
+    })
 }
 
-fn sys_writev(fd: i32, iov: *const api::ctypes::iovec, iocnt: i32) -> isize {
-    unsafe { api::sys_writev(fd, iov, iocnt) }
+fn sys_mremap(
+    old_addr: usize,
+    old_size: usize,
+    new_size: usize,
+    flags: i32,
+    _new_addr: usize,
+) -> isize {
+    syscall_body!(sys_mremap, {
+        let mremap_flags = MremapFlags::from_bits(flags)
+            .ok_or(LinuxError::EINVAL)?;
+        // Moving to a caller-specified address is not supported yet.
+        if mremap_flags.contains(MremapFlags::MREMAP_FIXED) {
+            return Err(LinuxError::EINVAL);
+        }
+
+        let old_start = VirtAddr::from(old_addr);
+        if !old_start.is_aligned_4k() {
+            return Err(LinuxError::EINVAL);
+        }
+        let old_size = (old_size + PAGE_SIZE_4K - 1) & !(PAGE_SIZE_4K - 1);
+        let new_size = (new_size + PAGE_SIZE_4K - 1) & !(PAGE_SIZE_4K - 1);
+        if old_size == 0 || new_size == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+
+        let curr = current();
+        let mut aspace = curr.task_ext().aspace.lock();
+        if !aspace.is_mapped(old_start, old_size) {
+            return Err(LinuxError::EFAULT);
+        }
+
+        // Shrink: drop the tail and keep the mapping where it is.
+        if new_size <= old_size {
+            if new_size < old_size {
+                aspace.unmap(old_start + new_size, old_size - new_size).map_err(ax_to_linux)?;
+            }
+            return Ok(old_start.as_usize() as isize);
+        }
+
+        let flags = aspace.area_flags(old_start).ok_or(LinuxError::EFAULT)?;
+        let grow_start = old_start + old_size;
+        let grow_size = new_size - old_size;
+
+        // Grow in place if the pages right after the mapping are free.
+        if aspace.contains_range(grow_start, grow_size) && !aspace.overlaps(grow_start, grow_size) {
+            aspace.map_alloc(grow_start, grow_size, flags, true).map_err(ax_to_linux)?;
+            return Ok(old_start.as_usize() as isize);
+        }
+
+        if !mremap_flags.contains(MremapFlags::MREMAP_MAYMOVE) {
+            return Err(LinuxError::ENOMEM);
+        }
+
+        // Move: map a new range, copy the old contents over and unmap the old range.
+        let limit = VirtAddrRange::from_start_size(aspace.base(), aspace.size());
+        let new_start = aspace
+            .find_free_area(aspace.base(), new_size, limit)
+            .ok_or(LinuxError::ENOMEM)?;
+        aspace.map_alloc(new_start, new_size, flags, true).map_err(ax_to_linux)?;
+
+        let mut data = vec![0u8; old_size];
+        aspace.read(old_start, &mut data).map_err(ax_to_linux)?;
+        aspace.write(new_start, &data).map_err(ax_to_linux)?;
+        aspace.unmap(old_start, old_size).map_err(ax_to_linux)?;
+        curr.task_ext()
+            .thread_group
+            .move_mapping(old_start.as_usize(), new_start.as_usize());
+
+        Ok(new_start.as_usize() as isize)
+    })
 }
 
-fn sys_set_tid_address(tid_ptd: *const i32) -> isize {
-    let curr = current();
-    curr.task_ext().set_clear_child_tid(tid_ptd as _);
-    curr.id().as_u64() as isize
+/// Returns the page-aligned range covering `[addr, addr + length)`.
+fn page_range(addr: usize, length: usize) -> LinuxResult<(VirtAddr, usize)> {
+    let end = addr.checked_add(length).ok_or(LinuxError::ENOMEM)?;
+    let start = VirtAddr::from(addr).align_down_4k();
+    let end = VirtAddr::from(end).align_up_4k();
+    Ok((start, end - start))
+}
+
+/// Locks the pages of a range into memory.
+///
+/// Mappings are populated eagerly, so this only checks that the range is
+/// mapped and records the pages as locked.
+fn sys_mlock(addr: usize, length: usize) -> isize {
+    syscall_body!(sys_mlock, {
+        let (start, size) = page_range(addr, length)?;
+        current().task_ext().aspace.lock().lock(start, size).map_err(ax_to_linux)?;
+        Ok(0)
+    })
+}
+
+fn sys_munlock(addr: usize, length: usize) -> isize {
+    syscall_body!(sys_munlock, {
+        let (start, size) = page_range(addr, length)?;
+        current().task_ext().aspace.lock().unlock(start, size).map_err(ax_to_linux)?;
+        Ok(0)
+    })
+}
+
+/// Reports which pages of `[addr, addr + length)` are resident, one byte per
+/// page in `vec` with bit 0 set for a resident page.
+fn sys_mincore(addr: usize, length: usize, vec: *mut u8) -> isize {
+    syscall_body!(sys_mincore, {
+        if !VirtAddr::from(addr).is_aligned_4k() {
+            return Err(LinuxError::EINVAL);
+        }
+        let (start, size) = page_range(addr, length)?;
+        let aspace = current().task_ext().aspace.lock();
+        if !aspace.is_mapped(start, size) {
+            return Err(LinuxError::ENOMEM);
+        }
+        let resident: Vec<u8> = (0..size)
+            .step_by(PAGE_SIZE_4K)
+            .map(|offset| aspace.page_table().query(start + offset).is_ok() as u8)
+            .collect();
+        copy_to_user(&aspace, vec as usize, &resident[..])?;
+        Ok(0)
+    })
 }
 
-fn sys_ioctl(_fd: i32, _op: usize, _argp: *mut c_void) -> i32 {
-    ax_println!("Ignore SYS_IOCTL");
-    0
+/// Gives advice about the use of the memory in `[addr, addr + length)`.
+///
+/// Only `MADV_DONTNEED` takes effect: the pages are zeroed, so the next access
+/// reads zeros as it would from freshly faulted-in anonymous memory.
+fn sys_madvise(addr: usize, length: usize, advice: i32) -> isize {
+    syscall_body!(sys_madvise, {
+        let start = VirtAddr::from(addr);
+        if !start.is_aligned_4k() {
+            return Err(LinuxError::EINVAL);
+        }
+        // Like Linux, a range that wraps once rounded up to pages is invalid.
+        let aligned_length = length
+            .checked_add(PAGE_SIZE_4K - 1)
+            .filter(|end| addr.checked_add(*end).is_some())
+            .ok_or(LinuxError::EINVAL)?
+            & !(PAGE_SIZE_4K - 1);
+        if aligned_length == 0 {
+            return Ok(0);
+        }
+
+        let curr = current();
+        let aspace = curr.task_ext().aspace.lock();
+        if !aspace.is_mapped(start, aligned_length) {
+            return Err(LinuxError::ENOMEM);
+        }
+
+        match advice {
+            MADV_NORMAL | MADV_RANDOM | MADV_SEQUENTIAL | MADV_WILLNEED => {}
+            MADV_DONTNEED => {
+                // Locked pages must keep their contents.
+                if (0..aligned_length)
+                    .step_by(PAGE_SIZE_4K)
+                    .any(|offset| aspace.is_locked(start + offset))
+                {
+                    return Err(LinuxError::EINVAL);
+                }
+                let zeros = [0u8; PAGE_SIZE_4K];
+                for offset in (0..aligned_length).step_by(PAGE_SIZE_4K) {
+                    aspace.write(start + offset, &zeros).map_err(ax_to_linux)?;
+                }
+            }
+            _ => return Err(LinuxError::EINVAL),
+        }
+        Ok(0)
+    })
+}
+
+/// Writes the absolute path of the current working directory into `buf`.
+///
+/// Returns the length of the path including the terminating NUL.
+fn sys_getcwd(buf: *mut c_char, size: usize) -> isize {
+    syscall_body!(sys_getcwd, {
+        let cwd = current().task_ext().thread_group.cwd();
+        let len = cwd.len() + 1;
+        if size < len {
+            return Err(LinuxError::ERANGE);
+        }
+        let mut path = cwd.into_bytes();
+        path.push(0);
+        copy_to_user(&current().task_ext().aspace.lock(), buf as usize, &path[..])?;
+        Ok(len)
+    })
+}
+
+/// Checks whether `path` exists and allows the accesses requested by `mode`.
+fn sys_faccessat(dirfd: c_int, path: *const c_char, mode: c_int) -> isize {
+    syscall_body!(sys_faccessat, {
+        if mode & !(R_OK | W_OK | X_OK) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = resolve_at(dirfd, user_str(path)?)?;
+        let perm = axfs::api::metadata(&path).map_err(ax_to_linux)?.permissions();
+        if mode == F_OK {
+            return Ok(0);
+        }
+        if (mode & R_OK != 0 && !perm.owner_readable())
+            || (mode & W_OK != 0 && !perm.owner_writable())
+            || (mode & X_OK != 0 && !perm.owner_executable())
+        {
+            return Err(LinuxError::EACCES);
+        }
+        Ok(0)
+    })
+}
+
+fn sys_chdir(path: *const c_char) -> isize {
+    syscall_body!(sys_chdir, {
+        axfs::api::set_current_dir(&user_str(path)?).map_err(ax_to_linux)?;
+        let mut cwd = axfs::api::current_dir().map_err(ax_to_linux)?;
+        // axfs keeps a trailing slash on directory paths
+        if cwd.len() > 1 && cwd.ends_with('/') {
+            cwd.pop();
+        }
+        current().task_ext().thread_group.set_cwd(cwd);
+        Ok(0)
+    })
+}
+
+fn sys_mkdirat(dirfd: c_int, path: *const c_char, mode: api::ctypes::mode_t) -> isize {
+    syscall_body!(sys_mkdirat, {
+        let path = resolve_at(dirfd, user_str(path)?)?;
+        let mode = mode & !current().task_ext().thread_group.umask();
+        debug!("sys_mkdirat <= {:?} {:#o}", path, mode);
+        axfs::api::create_dir(&path).map_err(ax_to_linux)?;
+        api::record_file_mode(&path, mode);
+        Ok(0)
+    })
+}
+
+fn sys_unlinkat(dirfd: c_int, path: *const c_char, flags: c_int) -> isize {
+    syscall_body!(sys_unlinkat, {
+        if flags & !AT_REMOVEDIR != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let path = resolve_at(dirfd, user_str(path)?)?;
+        if flags & AT_REMOVEDIR != 0 {
+            axfs::api::remove_dir(&path).map_err(ax_to_linux)?;
+            api::forget_file_mode(&path);
+        } else {
+            axfs::api::remove_file(&path).map_err(ax_to_linux)?;
+            api::forget_file_mode(&path);
+        }
+        Ok(0)
+    })
+}
+
+fn sys_renameat2(
+    old_dirfd: c_int,
+    old_path: *const c_char,
+    new_dirfd: c_int,
+    new_path: *const c_char,
+    flags: u32,
+) -> isize {
+    syscall_body!(sys_renameat2, {
+        if flags & !RENAME_NOREPLACE != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let old_path = resolve_at(old_dirfd, user_str(old_path)?)?;
+        let new_path = resolve_at(new_dirfd, user_str(new_path)?)?;
+        if flags & RENAME_NOREPLACE != 0 && axfs::api::metadata(&new_path).is_ok() {
+            return Err(LinuxError::EEXIST);
+        }
+        axfs::api::rename(&old_path, &new_path).map_err(ax_to_linux)?;
+        api::move_file_mode(&old_path, &new_path);
+        Ok(0)
+    })
+}
+
+/// Opens `fname` relative to `dfd`, creating it with `mode & !umask` if
+/// `O_CREAT` is given and it does not exist yet. An existing file keeps its
+/// mode.
+fn sys_openat(dfd: c_int, fname: *const c_char, flags: c_int, mode: api::ctypes::mode_t) -> isize {
+    syscall_body!(sys_openat, {
+        let mut path = resolve_at(dfd, user_str(fname)?)?.into_bytes();
+        path.push(0);
+        let mode = if flags as u32 & api::ctypes::O_CREAT != 0 {
+            mode & 0o7777 & !current().task_ext().thread_group.umask()
+        } else {
+            0
+        };
+        api_ret(api::sys_open(path.as_ptr() as _, flags, mode) as isize)
+    })
+}
+
+/// Sets the file mode creation mask and returns the previous one.
+fn sys_umask(mask: api::ctypes::mode_t) -> isize {
+    syscall_body!(sys_umask, {
+        Ok(current().task_ext().thread_group.set_umask(mask & 0o777))
+    })
+}
+
+/// Closes `fd`, failing with `EBADF` if it is not open.
+///
+/// The standard streams are never really closed, as the console stays in the
+/// fd table for the kernel too.
+fn sys_close(fd: i32) -> isize {
+    syscall_body!(sys_close, {
+        if fd < 0 {
+            return Err(LinuxError::EBADF);
+        }
+        api::get_file_like(fd)?;
+        api_ret(api::sys_close(fd) as isize)
+    })
+}
+
+fn sys_getdents64(fd: i32, dirp: *mut c_void, count: usize) -> isize {
+    syscall_body!(sys_getdents64, {
+        check_user_buf(&current().task_ext().aspace.lock(), dirp as usize, count, true)?;
+        api_ret(unsafe { api::sys_getdents64(fd, dirp, count) })
+    })
+}
+
+fn sys_lseek(fd: i32, offset: isize, whence: i32) -> isize {
+    syscall_body!(sys_lseek, {
+        if !matches!(whence, SEEK_SET | SEEK_CUR | SEEK_END) {
+            return Err(LinuxError::EINVAL);
+        }
+        api_ret(api::sys_lseek(fd, offset as _, whence) as isize)
+    })
+}
+
+/// Creates a pipe and writes its read and write ends into `fds`.
+fn sys_pipe2(fds: *mut i32, flags: i32) -> isize {
+    syscall_body!(sys_pipe2, {
+        let flags = flags as u32;
+        if flags & !(api::ctypes::O_CLOEXEC | api::ctypes::O_NONBLOCK) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        check_user_buf(&curr.task_ext().aspace.lock(), fds as usize, 2 * core::mem::size_of::<i32>(), true)?;
+
+        let mut pair = [0; 2];
+        api_ret(api::sys_pipe(&mut pair) as isize)?;
+        for fd in pair.iter() {
+            if flags & api::ctypes::O_NONBLOCK != 0 {
+                api::sys_fcntl(*fd, api::ctypes::F_SETFL as _, api::ctypes::O_NONBLOCK as _);
+            }
+            if flags & api::ctypes::O_CLOEXEC != 0 {
+                api::sys_fcntl(*fd, api::ctypes::F_SETFD as _, api::ctypes::FD_CLOEXEC as _);
+            }
+        }
+        copy_to_user(&curr.task_ext().aspace.lock(), fds as usize, &pair)?;
+        Ok(0)
+    })
+}
+
+fn sys_dup(old_fd: i32) -> isize {
+    syscall_body!(sys_dup, api_ret(api::sys_dup(old_fd) as isize))
+}
+
+fn sys_dup3(old_fd: i32, new_fd: i32, flags: i32) -> isize {
+    syscall_body!(sys_dup3, {
+        if old_fd == new_fd || flags & !(api::ctypes::O_CLOEXEC as i32) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if new_fd < 0 {
+            return Err(LinuxError::EBADF);
+        }
+        let fd = api_ret(api::sys_dup2(old_fd, new_fd) as isize)?;
+        if flags & (api::ctypes::O_CLOEXEC as i32) != 0 {
+            api::sys_fcntl(new_fd, api::ctypes::F_SETFD as _, api::ctypes::FD_CLOEXEC as _);
+        }
+        Ok(fd)
+    })
+}
+
+fn sys_fcntl(fd: i32, cmd: i32, arg: usize) -> isize {
+    syscall_body!(sys_fcntl, api_ret(api::sys_fcntl(fd, cmd, arg) as isize))
+}
+
+/// Reads up to `count` bytes from `fd`.
+///
+/// Like the other read and write syscalls, it returns the partial count of a
+/// short transfer, and only fails with `EINTR` if its thread was killed
+/// while blocking, before any byte was transferred.
+fn sys_read(fd: i32, buf: *mut c_void, count: usize) -> isize {
+    syscall_body!(sys_read, {
+        check_user_buf(&current().task_ext().aspace.lock(), buf as usize, count, true)?;
+        api_ret(api::sys_read(fd, buf, count))
+    })
+}
+
+/// Writes up to `count` bytes to `fd`, see [`sys_read`] for short writes.
+fn sys_write(fd: i32, buf: *const c_void, count: usize) -> isize {
+    syscall_body!(sys_write, {
+        check_user_buf(&current().task_ext().aspace.lock(), buf as usize, count, false)?;
+        api_ret(api::sys_write(fd, buf, count))
+    })
+}
+
+/// Reads into the buffers of `iov` in order, see [`sys_read`] for short reads.
+///
+/// An error after some bytes were read, such as `EINTR`, is dropped in
+/// favour of the partial count.
+fn sys_readv(fd: i32, iov: *const api::ctypes::iovec, iocnt: i32) -> isize {
+    syscall_body!(sys_readv, {
+        check_iovecs(iov, iocnt, true)?;
+        api_ret(unsafe { api::sys_readv(fd, iov, iocnt) })
+    })
+}
+
+/// Writes the buffers of `iov` in order, with the same short and interrupted
+/// transfers as [`sys_readv`].
+fn sys_writev(fd: i32, iov: *const api::ctypes::iovec, iocnt: i32) -> isize {
+    syscall_body!(sys_writev, {
+        check_iovecs(iov, iocnt, false)?;
+        api_ret(unsafe { api::sys_writev(fd, iov, iocnt) })
+    })
+}
+
+/// Checks the `iocnt` entries of `iov` and the buffers they point to, which
+/// must be writable if the kernel is going to `write` into them.
+fn check_iovecs(iov: *const api::ctypes::iovec, iocnt: i32, write: bool) -> LinuxResult {
+    if iocnt <= 0 {
+        return Ok(());
+    }
+    let curr = current();
+    let aspace = curr.task_ext().aspace.lock();
+    let size = iocnt as usize * core::mem::size_of::<api::ctypes::iovec>();
+    check_user_buf(&aspace, iov as usize, size, false)?;
+    for iov in unsafe { core::slice::from_raw_parts(iov, iocnt as usize) } {
+        check_user_buf(&aspace, iov.iov_base as usize, iov.iov_len as usize, write)?;
+    }
+    Ok(())
+}
+
+fn sys_pread64(fd: i32, buf: *mut c_void, count: usize, offset: isize) -> isize {
+    syscall_body!(sys_pread64, {
+        check_user_buf(&current().task_ext().aspace.lock(), buf as usize, count, true)?;
+        api_ret(api::sys_pread(fd, buf, count, offset as _))
+    })
+}
+
+fn sys_pwrite64(fd: i32, buf: *const c_void, count: usize, offset: isize) -> isize {
+    syscall_body!(sys_pwrite64, {
+        check_user_buf(&current().task_ext().aspace.lock(), buf as usize, count, false)?;
+        api_ret(api::sys_pwrite(fd, buf, count, offset as _))
+    })
+}
+
+/// Size of the kernel buffer that sys_sendfile copies through.
+const SENDFILE_CHUNK: usize = 0x1000;
+
+/// Copies up to `count` bytes from `in_fd` to `out_fd` through a kernel buffer.
+///
+/// With a non-null `offset`, `in_fd` is read with pread from `*offset` on,
+/// which is advanced instead of the file position. It stops early at end of
+/// file or on a short write, and like [`sys_readv`] returns the partial count
+/// rather than an error once some bytes have been transferred.
+///
+/// Without an offset `in_fd` must be seekable, so that the bytes read but not
+/// written can be given back. Otherwise it fails with `ESPIPE`.
+fn sys_sendfile(out_fd: c_int, in_fd: c_int, offset: *mut i64, count: usize) -> isize {
+    syscall_body!(sys_sendfile, {
+        api::get_file_like(in_fd)?;
+        api::get_file_like(out_fd)?;
+        let mut pos = if offset.is_null() {
+            if api::sys_lseek(in_fd, 0, SEEK_CUR) < 0 {
+                return Err(LinuxError::ESPIPE);
+            }
+            None
+        } else {
+            let pos = copy_from_user::<i64>(&current().task_ext().aspace.lock(), offset as usize)?;
+            if pos < 0 {
+                return Err(LinuxError::EINVAL);
+            }
+            Some(pos)
+        };
+
+        let mut buf = vec![0u8; count.min(SENDFILE_CHUNK)];
+        let mut sent = 0;
+        while sent < count {
+            let len = (count - sent).min(buf.len());
+            let nread = match pos {
+                Some(pos) => api::sys_pread(in_fd, buf.as_mut_ptr() as _, len, pos as _),
+                None => api::sys_read(in_fd, buf.as_mut_ptr() as _, len),
+            };
+            if nread < 0 && sent == 0 {
+                return api_ret(nread);
+            }
+            if nread <= 0 {
+                break;
+            }
+            let nwritten = api::sys_write(out_fd, buf.as_ptr() as _, nread as usize);
+            if nwritten < 0 && sent == 0 {
+                return api_ret(nwritten);
+            }
+            let nwritten = nwritten.max(0);
+            sent += nwritten as usize;
+            match pos.as_mut() {
+                Some(pos) => *pos += nwritten as i64,
+                // Give the bytes that were not written back to the input,
+                // which was checked to be seekable above.
+                None if nwritten < nread => {
+                    if api::sys_lseek(in_fd, (nwritten - nread) as _, SEEK_CUR) < 0 {
+                        warn!("sys_sendfile: lost {} bytes of fd {}", nread - nwritten, in_fd);
+                    }
+                }
+                None => {}
+            }
+            if nwritten < nread {
+                break;
+            }
+        }
+
+        if let Some(pos) = pos {
+            copy_to_user(&current().task_ext().aspace.lock(), offset as usize, &pos)?;
+        }
+        Ok(sent as isize)
+    })
+}
+
+/// Fills in the `revents` of `fds`, returning how many of them are ready.
+///
+/// Negative fds are skipped, and unknown ones report `POLLNVAL`.
+fn poll_fds(fds: &mut [PollFd]) -> usize {
+    let mut ready = 0;
+    for pfd in fds.iter_mut() {
+        pfd.revents = 0;
+        if pfd.fd < 0 {
+            continue;
+        }
+        pfd.revents = match api::get_file_like(pfd.fd).and_then(|f| f.poll()) {
+            Ok(state) => {
+                let mut revents = 0;
+                if state.readable {
+                    revents |= POLLIN;
+                }
+                if state.writable {
+                    revents |= POLLOUT;
+                }
+                revents & pfd.events
+            }
+            Err(_) => POLLNVAL,
+        };
+        if pfd.revents != 0 {
+            ready += 1;
+        }
+    }
+    ready
+}
+
+/// Waits until one of `fds` is ready, `tmo` elapses, or the thread is killed.
+///
+/// A null `tmo` waits forever and a zero one only probes, and without any
+/// fds it is a plain sleep that user memory is not touched for. Pipes and
+/// the console have no wait queues to sleep on, so readiness is polled
+/// between yields. `sigmask` replaces the signal mask for the duration of
+/// the call.
+fn sys_ppoll(
+    fds: *mut PollFd,
+    nfds: usize,
+    tmo: *const api::ctypes::timespec,
+    sigmask: *const SigSet,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_ppoll, {
+        if nfds > api::AX_FILE_LIMIT {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let (mut pfds, mask) = {
+            let aspace = curr.task_ext().aspace.lock();
+            let pfds = if nfds == 0 {
+                Vec::new()
+            } else {
+                check_user_buf(&aspace, fds as usize, nfds * core::mem::size_of::<PollFd>(), true)?;
+                copy_slice_from_user::<PollFd>(&aspace, fds as usize, nfds)?
+            };
+            let mask = if sigmask.is_null() {
+                None
+            } else {
+                check_sigset_size(sigsetsize)?;
+                Some(copy_from_user::<SigSet>(&aspace, sigmask as usize)?)
+            };
+            (pfds, mask)
+        };
+        let deadline = if tmo.is_null() {
+            None
+        } else {
+            Some(axhal::time::monotonic_time() + read_sleep_request(tmo)?)
+        };
+        let ready = poll_until(deadline, mask, || poll_fds(&mut pfds))?;
+        if nfds > 0 {
+            copy_to_user(&curr.task_ext().aspace.lock(), fds as usize, &pfds[..])?;
+        }
+        Ok(ready as isize)
+    })
+}
+
+/// Calls `poll` until it reports ready fds, `deadline` passes, or the thread
+/// is killed, with the signal mask replaced by `mask` in the meantime.
+///
+/// Pipes and the console have no wait queues to sleep on, so it yields
+/// between the calls.
+fn poll_until(
+    deadline: Option<Duration>,
+    mask: Option<SigSet>,
+    mut poll: impl FnMut() -> usize,
+) -> LinuxResult<usize> {
+    let curr = current();
+    let old_mask = curr.task_ext().sig_mask();
+    if let Some(mask) = mask {
+        curr.task_ext().set_sig_mask(mask);
+    }
+    let res = loop {
+        let ready = poll();
+        if ready > 0 {
+            break Ok(ready);
+        }
+        if deadline.is_some_and(|deadline| axhal::time::monotonic_time() >= deadline) {
+            break Ok(0);
+        }
+        if crate::signal::interrupted() {
+            break Err(LinuxError::EINTR);
+        }
+        axtask::yield_now();
+    };
+    curr.task_ext().set_sig_mask(old_mask);
+    res
+}
+
+/// Bits in each word of an `fd_set` of sys_pselect6.
+const FD_SET_BITS: usize = u64::BITS as usize;
+
+/// Waits until one of the fds in `readfds` or `writefds` is ready, like
+/// [`sys_ppoll`] does, and leaves only the ready ones in the sets.
+///
+/// `nfds` is capped to the size of the fd table. No exceptional conditions
+/// are ever reported, so `exceptfds` is always cleared.
+fn sys_pselect6(
+    nfds: c_int,
+    readfds: *mut u64,
+    writefds: *mut u64,
+    exceptfds: *mut u64,
+    tmo: *const api::ctypes::timespec,
+    sigmask: *const [usize; 2],
+) -> isize {
+    syscall_body!(sys_pselect6, {
+        let nfds = usize::try_from(nfds)
+            .map_err(|_| LinuxError::EINVAL)?
+            .min(api::AX_FILE_LIMIT);
+        let words = nfds.div_ceil(FD_SET_BITS);
+        let curr = current();
+        // The sets are copied in and out, as the user may unmap them or pass
+        // the same one twice while we wait.
+        let (mut rset, mut wset, mut eset, mask) = {
+            let aspace = curr.task_ext().aspace.lock();
+            let copy_set = |set: *mut u64| {
+                if set.is_null() {
+                    Ok(Vec::new())
+                } else {
+                    copy_slice_from_user::<u64>(&aspace, set as usize, words)
+                }
+            };
+            let (rset, wset, eset) = (
+                copy_set(readfds)?,
+                copy_set(writefds)?,
+                copy_set(exceptfds)?,
+            );
+            // The sixth argument points to a `{ const sigset_t *ss; size_t ss_len; }`.
+            let [set, size] = if sigmask.is_null() {
+                [0, 0]
+            } else {
+                copy_from_user::<[usize; 2]>(&aspace, sigmask as usize)?
+            };
+            let mask = if set == 0 {
+                None
+            } else {
+                check_sigset_size(size)?;
+                Some(copy_from_user::<SigSet>(&aspace, set)?)
+            };
+            (rset, wset, eset, mask)
+        };
+        let deadline = if tmo.is_null() {
+            None
+        } else {
+            Some(axhal::time::monotonic_time() + read_sleep_request(tmo)?)
+        };
+        let is_set = |set: &[u64], fd: usize| {
+            set.get(fd / FD_SET_BITS)
+                .is_some_and(|word| word >> (fd % FD_SET_BITS) & 1 != 0)
+        };
+
+        let mut fds: Vec<PollFd> = (0..nfds)
+            .filter_map(|fd| {
+                let mut events = 0;
+                if is_set(&rset, fd) {
+                    events |= POLLIN;
+                }
+                if is_set(&wset, fd) {
+                    events |= POLLOUT;
+                }
+                (events != 0).then_some(PollFd {
+                    fd: fd as c_int,
+                    events,
+                    revents: 0,
+                })
+            })
+            .collect();
+        poll_until(deadline, mask, || poll_fds(&mut fds))?;
+        if fds.iter().any(|pfd| pfd.revents & POLLNVAL != 0) {
+            return Err(LinuxError::EBADF);
+        }
+
+        rset.fill(0);
+        wset.fill(0);
+        eset.fill(0);
+        let mut ready = 0;
+        for pfd in fds.iter() {
+            let fd = pfd.fd as usize;
+            for (bits, event) in [(&mut rset, POLLIN), (&mut wset, POLLOUT)] {
+                if pfd.revents & event != 0 {
+                    bits[fd / FD_SET_BITS] |= 1 << (fd % FD_SET_BITS);
+                    ready += 1;
+                }
+            }
+        }
+        let aspace = curr.task_ext().aspace.lock();
+        for (set, bits) in [(readfds, rset), (writefds, wset), (exceptfds, eset)] {
+            if !set.is_null() {
+                copy_to_user(&aspace, set as usize, bits.as_slice())?;
+            }
+        }
+        Ok(ready)
+    })
+}
+
+fn write_statfs(stat: axfs::api::FileSystemStat, buf: *mut StatFs) -> LinuxResult<isize> {
+    let stat: StatFs = stat.into();
+    copy_to_user(&current().task_ext().aspace.lock(), buf as usize, &stat)?;
+    Ok(0)
+}
+
+fn sys_statfs(path: *const c_char, buf: *mut StatFs) -> isize {
+    syscall_body!(sys_statfs, {
+        write_statfs(axfs::api::statfs(&user_str(path)?).map_err(ax_to_linux)?, buf)
+    })
+}
+
+/// Like [`sys_statfs`], for the filesystem an open file is on.
+///
+/// Only files and directories know their filesystem, so pipes and the
+/// console give `EINVAL`.
+fn sys_fstatfs(fd: c_int, buf: *mut StatFs) -> isize {
+    syscall_body!(sys_fstatfs, {
+        let file = api::get_file_like(fd)?;
+        let path = file.path().ok_or(LinuxError::EINVAL)?;
+        write_statfs(axfs::api::statfs(path).map_err(ax_to_linux)?, buf)
+    })
+}
+
+fn sys_ftruncate(fd: i32, length: isize) -> isize {
+    syscall_body!(sys_ftruncate, api_ret(api::sys_ftruncate(fd, length as _) as isize))
+}
+
+/// Reads the target of the symbolic link `path` into `buf`.
+///
+/// The underlying filesystems have no symbolic links, so only the
+/// `/proc/self/exe` link to the running executable is provided.
+fn sys_readlinkat(dirfd: c_int, path: *const c_char, buf: *mut c_char, size: usize) -> isize {
+    syscall_body!(sys_readlinkat, {
+        let path = resolve_at(dirfd, user_str(path)?)?;
+        if size == 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let target = match &*path {
+            "/proc/self/exe" => crate::USER_APP_PATH,
+            _ => {
+                axfs::api::metadata(&path).map_err(ax_to_linux)?;
+                return Err(LinuxError::EINVAL);
+            }
+        };
+        let len = target.len().min(size);
+        copy_to_user(&current().task_ext().aspace.lock(), buf as usize, &target.as_bytes()[..len])?;
+        Ok(len)
+    })
+}
+
+fn sys_fstat(fd: i32, statbuf: *mut KernelStat) -> isize {
+    syscall_body!(sys_fstat, {
+        let st: KernelStat = api::get_file_like(fd)?.stat()?.into();
+        copy_to_user(&current().task_ext().aspace.lock(), statbuf as usize, &st)?;
+        Ok(0)
+    })
+}
+
+fn sys_fsync(fd: i32) -> isize {
+    syscall_body!(sys_fsync, api_ret(api::sys_fsync(fd) as isize))
+}
+
+fn sys_set_tid_address(tid_ptd: *const i32) -> isize {
+    syscall_body!(sys_set_tid_address, {
+        let curr = current();
+        curr.task_ext().set_clear_child_tid(tid_ptd as _);
+        Ok(curr.id().as_u64())
+    })
+}
+
+/// Waits on or wakes the futex word at `uaddr`.
+///
+/// Only `FUTEX_WAIT`, with an optional relative `timeout`, and `FUTEX_WAKE`
+/// are supported. All threads share the address space, so every futex is
+/// private and `FUTEX_PRIVATE_FLAG` makes no difference.
+fn sys_futex(uaddr: *const u32, op: c_int, val: u32, timeout: *const api::ctypes::timespec) -> isize {
+    syscall_body!(sys_futex, {
+        let uaddr = uaddr as usize;
+        if uaddr % core::mem::align_of::<u32>() != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        check_user_buf(&curr.task_ext().aspace.lock(), uaddr, 4, false)?;
+        match op & !FUTEX_PRIVATE_FLAG {
+            FUTEX_WAIT => {
+                let timeout = if timeout.is_null() {
+                    None
+                } else {
+                    Some(read_sleep_request(timeout)?)
+                };
+                crate::futex::futex_wait(uaddr, val, timeout)?;
+                Ok(0)
+            }
+            FUTEX_WAKE => Ok(crate::futex::futex_wake(uaddr, val as usize) as isize),
+            _ => Err(LinuxError::ENOSYS),
+        }
+    })
+}
+
+fn sys_clock_gettime(clk: api::ctypes::clockid_t, ts: *mut api::ctypes::timespec) -> isize {
+    syscall_body!(sys_clock_gettime, {
+        check_user_buf(
+            &current().task_ext().aspace.lock(),
+            ts as usize,
+            core::mem::size_of::<api::ctypes::timespec>(),
+            true,
+        )?;
+        api_ret(unsafe { api::sys_clock_gettime(clk, ts) } as isize)
+    })
+}
+
+/// Reports the resolution of the clock `clk`, which is one timer tick for
+/// both supported clocks. A null `res` only checks that `clk` is valid.
+fn sys_clock_getres(clk: api::ctypes::clockid_t, res: *mut api::ctypes::timespec) -> isize {
+    syscall_body!(sys_clock_getres, {
+        if !matches!(clk as u32, api::ctypes::CLOCK_MONOTONIC | api::ctypes::CLOCK_REALTIME) {
+            return Err(LinuxError::EINVAL);
+        }
+        if !res.is_null() {
+            let tick = Duration::from_nanos(axhal::time::ticks_to_nanos(1).max(1));
+            let tick: api::ctypes::timespec = tick.into();
+            copy_to_user(&current().task_ext().aspace.lock(), res as usize, &tick)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Reads and validates a sleep request from user space.
+fn read_sleep_request(req: *const api::ctypes::timespec) -> LinuxResult<Duration> {
+    let req: api::ctypes::timespec =
+        copy_from_user(&current().task_ext().aspace.lock(), req as usize)?;
+    if req.tv_sec < 0 || !(0..1_000_000_000).contains(&req.tv_nsec) {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(Duration::from(req))
+}
+
+/// Sleeps for `dur`, reporting the remaining time in `rem` on an early wakeup.
+fn sleep_relative(dur: Duration, rem: *mut api::ctypes::timespec) -> LinuxResult<isize> {
+    let now = axhal::time::monotonic_time();
+    axtask::sleep(dur);
+    let actual = axhal::time::monotonic_time() - now;
+
+    // Woken up early: report the remaining time if the caller asked for it.
+    if let Some(diff) = dur.checked_sub(actual).filter(|diff| !diff.is_zero()) {
+        if !rem.is_null() {
+            let diff: api::ctypes::timespec = diff.into();
+            copy_to_user(&current().task_ext().aspace.lock(), rem as usize, &diff)?;
+        }
+        return Err(LinuxError::EINTR);
+    }
+    Ok(0)
+}
+
+fn sys_nanosleep(req: *const api::ctypes::timespec, rem: *mut api::ctypes::timespec) -> isize {
+    syscall_body!(sys_nanosleep, {
+        let dur = read_sleep_request(req)?;
+        sleep_relative(dur, rem)
+    })
+}
+
+/// Sleeps on the clock `clk`, either for a duration or until an absolute
+/// deadline if `TIMER_ABSTIME` is given.
+fn sys_clock_nanosleep(
+    clk: api::ctypes::clockid_t,
+    flags: c_int,
+    req: *const api::ctypes::timespec,
+    rem: *mut api::ctypes::timespec,
+) -> isize {
+    syscall_body!(sys_clock_nanosleep, {
+        let now = match clk as u32 {
+            api::ctypes::CLOCK_MONOTONIC => axhal::time::monotonic_time(),
+            api::ctypes::CLOCK_REALTIME => axhal::time::wall_time(),
+            _ => return Err(LinuxError::EINVAL),
+        };
+        if flags & !TIMER_ABSTIME != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let req = read_sleep_request(req)?;
+        if flags & TIMER_ABSTIME == 0 {
+            return sleep_relative(req, rem);
+        }
+        // The deadline is on `clk`, but tasks always sleep on the monotonic clock.
+        if let Some(dur) = req.checked_sub(now) {
+            axtask::sleep_until(axhal::time::monotonic_time() + dur);
+        }
+        Ok(0)
+    })
+}
+
+fn sys_sched_yield() -> isize {
+    syscall_body!(sys_sched_yield, {
+        axtask::yield_now();
+        Ok(0)
+    })
+}
+
+fn nanos_to_ticks(ns: u64) -> isize {
+    (ns / (axhal::time::NANOS_PER_SEC / USER_HZ)) as isize
+}
+
+/// Reports the CPU time of the calling task and returns the ticks since boot.
+///
+/// There are no child processes to wait for, so the children's times are zero.
+fn sys_times(buf: *mut Tms) -> isize {
+    syscall_body!(sys_times, {
+        if !buf.is_null() {
+            let (utime, stime) = current().task_ext().cpu_times();
+            let tms = Tms {
+                tms_utime: nanos_to_ticks(utime),
+                tms_stime: nanos_to_ticks(stime),
+                tms_cutime: 0,
+                tms_cstime: 0,
+            };
+            copy_to_user(&current().task_ext().aspace.lock(), buf as usize, &tms)?;
+        }
+        Ok(nanos_to_ticks(axhal::time::monotonic_time_nanos()))
+    })
+}
+
+/// Sends a signal to a process.
+///
+/// There is a single process, which can be named by its pid, by 0 (its
+/// process group), or by -1 (every process it may signal).
+fn sys_kill(pid: i32, sig: c_int) -> isize {
+    syscall_body!(sys_kill, {
+        let sig = crate::signal::check_signal(sig)?;
+        let curr = current();
+        if pid != curr.task_ext().proc_id as i32 && pid != 0 && pid != -1 {
+            return Err(LinuxError::ESRCH);
+        }
+        crate::signal::send_to_process(curr.as_task_ref(), sig);
+        Ok(0)
+    })
+}
+
+/// Sends a signal to a thread of the current process.
+///
+/// Unlike Linux, a fatal signal only terminates the target thread rather than
+/// its whole process, which is how spawned tasks are stopped.
+fn sys_tkill(tid: i32, sig: c_int) -> isize {
+    syscall_body!(sys_tkill, {
+        let sig = crate::signal::check_signal(sig)?;
+        if tid <= 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        let task = current()
+            .task_ext()
+            .thread_group
+            .find(tid as u64)
+            .ok_or(LinuxError::ESRCH)?;
+        crate::signal::send_to_thread(&task, sig);
+        Ok(0)
+    })
+}
+
+/// Checks the `sigsetsize` argument of the `rt_sig*` syscalls.
+fn check_sigset_size(size: usize) -> LinuxResult {
+    if size != core::mem::size_of::<SigSet>() {
+        return Err(LinuxError::EINVAL);
+    }
+    Ok(())
+}
+
+/// Examines and changes the action of a signal.
+///
+/// The actions are only recorded: handlers are never invoked yet.
+fn sys_rt_sigaction(
+    sig: c_int,
+    act: *const SigAction,
+    oldact: *mut SigAction,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_rt_sigaction, {
+        check_sigset_size(sigsetsize)?;
+        let sig = crate::signal::check_signal(sig)?;
+        if sig == 0 || (!act.is_null() && sig_bit(sig) & UNCATCHABLE != 0) {
+            return Err(LinuxError::EINVAL);
+        }
+        let curr = current();
+        let new = {
+            let aspace = curr.task_ext().aspace.lock();
+            if !oldact.is_null() {
+                check_user_buf(&aspace, oldact as usize, core::mem::size_of::<SigAction>(), true)?;
+            }
+            if act.is_null() {
+                None
+            } else {
+                Some(copy_from_user::<SigAction>(&aspace, act as usize)?)
+            }
+        };
+        let group = &curr.task_ext().thread_group;
+        let old = match new {
+            Some(new) => group.set_sig_action(sig, new),
+            None => group.sig_action(sig),
+        };
+        if !oldact.is_null() {
+            copy_to_user(&curr.task_ext().aspace.lock(), oldact as usize, &old)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Examines and changes the blocked signals of the current thread.
+fn sys_rt_sigprocmask(
+    how: c_int,
+    set: *const SigSet,
+    oldset: *mut SigSet,
+    sigsetsize: usize,
+) -> isize {
+    syscall_body!(sys_rt_sigprocmask, {
+        check_sigset_size(sigsetsize)?;
+        let curr = current();
+        let aspace = curr.task_ext().aspace.lock();
+        if !oldset.is_null() {
+            check_user_buf(&aspace, oldset as usize, core::mem::size_of::<SigSet>(), true)?;
+        }
+        let old = curr.task_ext().sig_mask();
+        if !set.is_null() {
+            let set: SigSet = copy_from_user(&aspace, set as usize)?;
+            let mask = match how {
+                SIG_BLOCK => old | set,
+                SIG_UNBLOCK => old & !set,
+                SIG_SETMASK => set,
+                _ => return Err(LinuxError::EINVAL),
+            };
+            curr.task_ext().set_sig_mask(mask);
+        }
+        if !oldset.is_null() {
+            copy_to_user(&aspace, oldset as usize, &old)?;
+        }
+        Ok(0)
+    })
+}
+
+/// Writes the mappings of the current process to `buf`, in the style of
+/// `/proc/self/maps` with one `start-end rwxp offset fd` line per area.
+///
+/// Areas not created by `mmap` (the program image, stack and heap) are
+/// reported as private anonymous ones. Returns the length of the dump, or
+/// `ERANGE` if it does not fit into `len` bytes.
+fn sys_dump_maps(buf: *mut u8, len: usize) -> isize {
+    use core::fmt::Write;
+
+    syscall_body!(sys_dump_maps, {
+        let curr = current();
+        let group = &curr.task_ext().thread_group;
+        let aspace = curr.task_ext().aspace.lock();
+        let mut dump = String::new();
+        for (range, flags) in aspace.areas() {
+            let record = group.mapping_at(range.start.as_usize());
+            let perm = |flag, c| if flags.contains(flag) { c } else { '-' };
+            let _ = writeln!(
+                dump,
+                "{:016x}-{:016x} {}{}{}{} {:08x} {}",
+                range.start.as_usize(),
+                range.end.as_usize(),
+                perm(MappingFlags::READ, 'r'),
+                perm(MappingFlags::WRITE, 'w'),
+                perm(MappingFlags::EXECUTE, 'x'),
+                if record.is_some_and(|r| r.shared) { 's' } else { 'p' },
+                record.map_or(0, |r| r.offset),
+                record.map_or(-1, |r| r.fd),
+            );
+        }
+        if dump.len() > len {
+            return Err(LinuxError::ERANGE);
+        }
+        copy_to_user(&aspace, buf as usize, dump.as_bytes())?;
+        Ok(dump.len() as isize)
+    })
+}
+
+fn sys_uname(name: *mut UtsName) -> isize {
+    syscall_body!(sys_uname, {
+        let uts = UtsName {
+            sysname: UtsName::field("ArceOS"),
+            nodename: UtsName::field("arceos"),
+            release: UtsName::field("10.0.0"),
+            version: UtsName::field("#1 ArceOS"),
+            machine: UtsName::field(if cfg!(target_arch = "riscv64") {
+                "riscv64"
+            } else if cfg!(target_arch = "aarch64") {
+                "aarch64"
+            } else if cfg!(target_arch = "x86_64") {
+                "x86_64"
+            } else {
+                "unknown"
+            }),
+            domainname: UtsName::field("(none)"),
+        };
+        copy_to_user(&current().task_ext().aspace.lock(), name as usize, &uts)?;
+        Ok(0)
+    })
+}
+
+/// Gets and sets the resource limits of the process `pid`.
+///
+/// The limits are recorded per process; `RLIMIT_NOFILE` can not be raised
+/// above the size of the fd table.
+fn sys_prlimit64(pid: i32, resource: u32, new_limit: *const RLimit, old_limit: *mut RLimit) -> isize {
+    use api::ctypes::{RLIMIT_AS, RLIMIT_NOFILE, RLIMIT_STACK};
+
+    syscall_body!(sys_prlimit64, {
+        let curr = current();
+        if pid != 0 && pid as usize != curr.task_ext().proc_id {
+            return Err(LinuxError::ESRCH);
+        }
+        if !matches!(resource, RLIMIT_STACK | RLIMIT_NOFILE | RLIMIT_AS) {
+            return Err(LinuxError::EINVAL);
+        }
+        let aspace = curr.task_ext().aspace.lock();
+        let new_limit = if new_limit.is_null() {
+            None
+        } else {
+            let limit: RLimit = copy_from_user(&aspace, new_limit as usize)?;
+            if limit.rlim_cur > limit.rlim_max {
+                return Err(LinuxError::EINVAL);
+            }
+            if resource == RLIMIT_NOFILE && limit.rlim_max > api::AX_FILE_LIMIT as u64 {
+                return Err(LinuxError::EPERM);
+            }
+            Some(limit)
+        };
+        let group = &curr.task_ext().thread_group;
+        if !old_limit.is_null() {
+            copy_to_user(&aspace, old_limit as usize, &group.rlimit(resource))?;
+        }
+        if let Some(limit) = new_limit {
+            group.set_rlimit(resource, limit);
+        }
+        Ok(0)
+    })
+}
+
+fn sys_getpid() -> isize {
+    syscall_body!(sys_getpid, Ok(current().task_ext().proc_id))
+}
+
+/// The only user of this single-user OS, which is root.
+const ROOT_ID: isize = 0;
+
+fn sys_getuid() -> isize {
+    syscall_body!(sys_getuid, Ok(ROOT_ID))
+}
+
+fn sys_geteuid() -> isize {
+    syscall_body!(sys_geteuid, Ok(ROOT_ID))
+}
+
+fn sys_getgid() -> isize {
+    syscall_body!(sys_getgid, Ok(ROOT_ID))
+}
+
+fn sys_getegid() -> isize {
+    syscall_body!(sys_getegid, Ok(ROOT_ID))
+}
+
+fn sys_gettid() -> isize {
+    syscall_body!(sys_gettid, Ok(current().id().as_u64()))
+}
+
+/// Reports uptime and memory usage, the latter in bytes of the global allocator.
+///
+/// `totalram` covers all pages managed by the allocator, while `freeram`
+/// also counts the free space left in its byte heap.
+fn sys_sysinfo(info: *mut SysInfo) -> isize {
+    syscall_body!(sys_sysinfo, {
+        let allocator = axalloc::global_allocator();
+        let total_pages = allocator.used_pages() + allocator.available_pages();
+        let sysinfo = SysInfo {
+            uptime: axhal::time::monotonic_time().as_secs() as isize,
+            totalram: total_pages * PAGE_SIZE_4K,
+            freeram: allocator.stats().free_bytes,
+            procs: 1,
+            mem_unit: 1,
+            ..Default::default()
+        };
+        copy_to_user(&current().task_ext().aspace.lock(), info as usize, &sysinfo)?;
+        Ok(0)
+    })
+}
+
+/// Issues a memory barrier on behalf of the threads of the process.
+///
+/// `MEMBARRIER_CMD_QUERY` returns the supported commands, and the others all
+/// issue a full fence. Registration is accepted but not required, and no
+/// other core is interrupted: a thread running there synchronizes with this
+/// one at the latest when it next enters the kernel.
+fn sys_membarrier(cmd: c_int, flags: u32, _cpu_id: c_int) -> isize {
+    syscall_body!(sys_membarrier, {
+        if flags != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        match cmd {
+            MEMBARRIER_CMD_QUERY => Ok(MEMBARRIER_SUPPORTED),
+            _ if cmd & MEMBARRIER_SUPPORTED == cmd && cmd.count_ones() == 1 => {
+                core::sync::atomic::fence(Ordering::SeqCst);
+                Ok(0)
+            }
+            _ => Err(LinuxError::EINVAL),
+        }
+    })
+}
+
+/// Writes the used, free and peak bytes of the global allocator to `stats`.
+///
+/// User memory counts as used, since its frames come from the same allocator.
+fn sys_alloc_stats(stats: *mut AllocStats) -> isize {
+    syscall_body!(sys_alloc_stats, {
+        let snapshot = axalloc::global_allocator().stats();
+        let out = AllocStats {
+            used: snapshot.used_bytes as u64,
+            free: snapshot.free_bytes as u64,
+            peak: snapshot.peak_bytes as u64,
+        };
+        copy_to_user(&current().task_ext().aspace.lock(), stats as usize, &out)?;
+        Ok(0)
+    })
+}
+
+/// Fills the user buffer with random bytes.
+///
+/// The bytes come from the PRNG in `axhal::misc::random`, which is always
+/// seeded, so `GRND_NONBLOCK` never has to fail with `EAGAIN`.
+fn sys_getrandom(buf: *mut u8, buflen: usize, flags: u32) -> isize {
+    syscall_body!(sys_getrandom, {
+        if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0 {
+            return Err(LinuxError::EINVAL);
+        }
+        if buflen == 0 {
+            return Ok(0);
+        }
+        let curr = current();
+        let aspace = curr.task_ext().aspace.lock();
+        check_user_buf(&aspace, buf as usize, buflen, true)?;
+
+        let mut bytes = vec![0u8; buflen];
+        for chunk in bytes.chunks_mut(core::mem::size_of::<u128>()) {
+            let random = axhal::misc::random().to_ne_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+        copy_to_user(&aspace, buf as usize, &bytes[..])?;
+        Ok(buflen as isize)
+    })
+}
+
+fn sys_ioctl(fd: i32, op: usize, argp: *mut c_void) -> isize {
+    syscall_body!(sys_ioctl, {
+        match op {
+            FIONBIO => {
+                let nonblocking =
+                    copy_from_user::<c_int>(&current().task_ext().aspace.lock(), argp as usize)? != 0;
+                let flags = api_ret(api::sys_fcntl(fd, api::ctypes::F_GETFL as _, 0) as isize)?;
+                let flags = if nonblocking {
+                    flags as u32 | api::ctypes::O_NONBLOCK
+                } else {
+                    flags as u32 & !api::ctypes::O_NONBLOCK
+                };
+                return api_ret(api::sys_fcntl(fd, api::ctypes::F_SETFL as _, flags as _) as isize);
+            }
+            FIONREAD => {
+                let nread = api::get_file_like(fd)?.nread()?;
+                let nread = nread.min(c_int::MAX as usize) as c_int;
+                copy_to_user(&current().task_ext().aspace.lock(), argp as usize, &nread)?;
+                return Ok(0);
+            }
+            _ => {}
+        }
+        if api::sys_isatty(fd) <= 0 {
+            api::get_file_like(fd)?;
+            return Err(LinuxError::ENOTTY);
+        }
+        match op {
+            TIOCGWINSZ => {
+                copy_to_user(&current().task_ext().aspace.lock(), argp as usize, &WinSize::CONSOLE)?;
+                Ok(0)
+            }
+            // the console has no terminal attributes to change
+            TCSETS | TCSETSW | TCSETSF => Ok(0),
+            _ => {
+                warn!("Unsupported ioctl {:#x} on fd {}", op, fd);
+                Err(LinuxError::ENOTTY)
+            }
+        }
+    })
 }
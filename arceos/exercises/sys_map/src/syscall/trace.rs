@@ -0,0 +1,194 @@
+//! strace-style logging of syscalls, enabled by the `strace` feature.
+
+use alloc::string::String;
+use core::fmt::Write;
+
+use super::*;
+
+/// How a syscall argument is printed.
+#[derive(Clone, Copy)]
+enum Arg {
+    /// Signed decimal, also used for file descriptors.
+    Int,
+    /// Unsigned decimal, used for sizes.
+    UInt,
+    /// Hexadecimal, used for flags without a decoder.
+    Hex,
+    /// Octal, used for file modes.
+    Oct,
+    /// User pointer.
+    Ptr,
+    /// Directory fd of the `*at` syscalls.
+    DirFd,
+    /// NUL-terminated user string.
+    Str,
+    /// `O_*` open flags.
+    OpenFlags,
+}
+
+use Arg::*;
+
+/// Strings longer than this are cut off in the trace.
+const MAX_STR_LEN: usize = 32;
+
+/// Returns the name and argument layout of the syscall `num`.
+fn signature(num: usize) -> Option<(&'static str, &'static [Arg])> {
+    let sig: (&'static str, &'static [Arg]) = match num {
+        SYS_GETCWD => ("getcwd", &[Ptr, UInt]),
+        SYS_DUP => ("dup", &[Int]),
+        SYS_DUP3 => ("dup3", &[Int, Int, OpenFlags]),
+        SYS_FCNTL => ("fcntl", &[Int, Int, Hex]),
+        SYS_IOCTL => ("ioctl", &[Int, Hex, Ptr]),
+        SYS_MKDIRAT => ("mkdirat", &[DirFd, Str, Oct]),
+        SYS_UNLINKAT => ("unlinkat", &[DirFd, Str, Hex]),
+        SYS_STATFS => ("statfs", &[Str, Ptr]),
+        SYS_FSTATFS => ("fstatfs", &[Int, Ptr]),
+        SYS_FTRUNCATE => ("ftruncate", &[Int, Int]),
+        SYS_FACCESSAT => ("faccessat", &[DirFd, Str, Int]),
+        SYS_CHDIR => ("chdir", &[Str]),
+        SYS_OPENAT => ("openat", &[DirFd, Str, OpenFlags, Oct]),
+        SYS_CLOSE => ("close", &[Int]),
+        SYS_PIPE2 => ("pipe2", &[Ptr, OpenFlags]),
+        SYS_GETDENTS64 => ("getdents64", &[Int, Ptr, UInt]),
+        SYS_LSEEK => ("lseek", &[Int, Int, Int]),
+        SYS_READ => ("read", &[Int, Ptr, UInt]),
+        SYS_WRITE => ("write", &[Int, Ptr, UInt]),
+        SYS_READV => ("readv", &[Int, Ptr, Int]),
+        SYS_WRITEV => ("writev", &[Int, Ptr, Int]),
+        SYS_PREAD64 => ("pread64", &[Int, Ptr, UInt, Int]),
+        SYS_PWRITE64 => ("pwrite64", &[Int, Ptr, UInt, Int]),
+        SYS_SENDFILE => ("sendfile", &[Int, Int, Ptr, UInt]),
+        SYS_PSELECT6 => ("pselect6", &[Int, Ptr, Ptr, Ptr, Ptr, Ptr]),
+        SYS_PPOLL => ("ppoll", &[Ptr, UInt, Ptr, Ptr, UInt]),
+        SYS_READLINKAT => ("readlinkat", &[DirFd, Str, Ptr, UInt]),
+        SYS_FSTAT => ("fstat", &[Int, Ptr]),
+        SYS_FSYNC => ("fsync", &[Int]),
+        SYS_EXIT => ("exit", &[Int]),
+        SYS_EXIT_GROUP => ("exit_group", &[Int]),
+        SYS_SET_TID_ADDRESS => ("set_tid_address", &[Ptr]),
+        SYS_FUTEX => ("futex", &[Ptr, Int, UInt, Ptr]),
+        SYS_NANOSLEEP => ("nanosleep", &[Ptr, Ptr]),
+        SYS_CLOCK_GETTIME => ("clock_gettime", &[Int, Ptr]),
+        SYS_CLOCK_GETRES => ("clock_getres", &[Int, Ptr]),
+        SYS_CLOCK_NANOSLEEP => ("clock_nanosleep", &[Int, Hex, Ptr, Ptr]),
+        SYS_SCHED_YIELD => ("sched_yield", &[]),
+        SYS_TIMES => ("times", &[Ptr]),
+        SYS_KILL => ("kill", &[Int, Int]),
+        SYS_TKILL => ("tkill", &[Int, Int]),
+        SYS_RT_SIGACTION => ("rt_sigaction", &[Int, Ptr, Ptr, UInt]),
+        SYS_RT_SIGPROCMASK => ("rt_sigprocmask", &[Int, Ptr, Ptr, UInt]),
+        SYS_UNAME => ("uname", &[Ptr]),
+        SYS_UMASK => ("umask", &[Oct]),
+        SYS_GETPID => ("getpid", &[]),
+        SYS_GETUID => ("getuid", &[]),
+        SYS_GETEUID => ("geteuid", &[]),
+        SYS_GETGID => ("getgid", &[]),
+        SYS_GETEGID => ("getegid", &[]),
+        SYS_GETTID => ("gettid", &[]),
+        SYS_SYSINFO => ("sysinfo", &[Ptr]),
+        SYS_BRK => ("brk", &[Ptr]),
+        SYS_CLONE => ("clone", &[Hex, Ptr, Ptr, Ptr, Ptr]),
+        SYS_MREMAP => ("mremap", &[Ptr, UInt, UInt, Hex, Ptr]),
+        SYS_MMAP => ("mmap", &[Ptr, UInt, Hex, Hex, Int, Int]),
+        SYS_MLOCK => ("mlock", &[Ptr, UInt]),
+        SYS_MUNLOCK => ("munlock", &[Ptr, UInt]),
+        SYS_MINCORE => ("mincore", &[Ptr, UInt, Ptr]),
+        SYS_MADVISE => ("madvise", &[Ptr, UInt, Int]),
+        SYS_WAIT4 => ("wait4", &[Int, Ptr, Hex, Ptr]),
+        SYS_PRLIMIT64 => ("prlimit64", &[Int, Int, Ptr, Ptr]),
+        SYS_RENAMEAT2 => ("renameat2", &[DirFd, Str, DirFd, Str, Hex]),
+        SYS_GETRANDOM => ("getrandom", &[Ptr, UInt, Hex]),
+        SYS_MEMBARRIER => ("membarrier", &[Hex, Hex, Int]),
+        SYS_SYSCALL_COUNT => ("syscall_count", &[UInt]),
+        SYS_DUMP_MAPS => ("dump_maps", &[Ptr, UInt]),
+        SYS_ALLOC_STATS => ("alloc_stats", &[Ptr]),
+        _ => return None,
+    };
+    Some(sig)
+}
+
+fn write_open_flags(out: &mut String, flags: u32) {
+    use api::ctypes::{O_APPEND, O_CLOEXEC, O_CREAT, O_DIRECTORY, O_EXCL, O_NONBLOCK, O_RDWR, O_TRUNC, O_WRONLY};
+
+    let mut names = alloc::vec![match flags & 0b11 {
+        O_WRONLY => "O_WRONLY",
+        O_RDWR => "O_RDWR",
+        _ => "O_RDONLY",
+    }];
+    let mut rest = flags & !0b11;
+    for (flag, name) in [
+        (O_CREAT, "O_CREAT"),
+        (O_EXCL, "O_EXCL"),
+        (O_TRUNC, "O_TRUNC"),
+        (O_APPEND, "O_APPEND"),
+        (O_NONBLOCK, "O_NONBLOCK"),
+        (O_DIRECTORY, "O_DIRECTORY"),
+        (O_CLOEXEC, "O_CLOEXEC"),
+    ] {
+        if rest & flag != 0 {
+            names.push(name);
+            rest &= !flag;
+        }
+    }
+    out.push_str(&names.join("|"));
+    if rest != 0 {
+        let _ = write!(out, "|{:#x}", rest);
+    }
+}
+
+fn write_arg(out: &mut String, arg: Arg, val: usize) {
+    let _ = match arg {
+        Int => write!(out, "{}", val as isize),
+        UInt => write!(out, "{}", val),
+        Hex => write!(out, "{:#x}", val),
+        Oct if val == 0 => write!(out, "0"),
+        Oct => write!(out, "0{:o}", val),
+        Ptr if val == 0 => write!(out, "NULL"),
+        Ptr => write!(out, "{:#x}", val),
+        DirFd if val as c_int == AT_FDCWD => write!(out, "AT_FDCWD"),
+        DirFd => write!(out, "{}", val as c_int),
+        Str => match user_str(val as *const c_char) {
+            Ok(s) if s.len() > MAX_STR_LEN => {
+                let mut end = MAX_STR_LEN;
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+                write!(out, "{:?}...", &s[..end])
+            }
+            Ok(s) => write!(out, "{:?}", s),
+            Err(_) => write!(out, "{:#x}", val),
+        },
+        OpenFlags => {
+            write_open_flags(out, val as u32);
+            Ok(())
+        }
+    };
+}
+
+/// Formats the syscall `num` with its decoded arguments, e.g.
+/// `openat(AT_FDCWD, "foo", O_RDONLY, 0)`.
+///
+/// Syscalls that never return are printed right away, as `exit(0) = ?`.
+pub(super) fn trace_syscall(num: usize, args: &[usize; 6]) -> String {
+    let mut out = String::new();
+    match signature(num) {
+        Some((name, kinds)) => {
+            out.push_str(name);
+            out.push('(');
+            for (i, (&kind, &val)) in kinds.iter().zip(args).enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_arg(&mut out, kind, val);
+            }
+            out.push(')');
+        }
+        None => {
+            let _ = write!(out, "syscall_{}({:#x}, {:#x}, {:#x}, ...)", num, args[0], args[1], args[2]);
+        }
+    }
+    if matches!(num, SYS_EXIT | SYS_EXIT_GROUP) {
+        ax_println!("{} = ?", out);
+    }
+    out
+}
@@ -1,13 +1,296 @@
 #![allow(dead_code)]
 
-use core::sync::atomic::AtomicU64;
+use core::sync::atomic::{AtomicU32, AtomicU64};
 
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 
+use arceos_posix_api as api;
 use axhal::arch::UspaceContext;
+use axerrno::{LinuxError, LinuxResult};
 use axmm::AddrSpace;
 use axsync::Mutex;
-use axtask::{AxTaskRef, TaskExtRef, TaskInner};
+use axtask::{AxTaskRef, TaskExtRef, TaskInner, WaitQueue};
+
+use crate::signal::{SigAction, SigSet, NSIG, UNCATCHABLE};
+
+/// Soft and hard limits of a resource, laid out as `struct rlimit`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RLimit {
+    pub rlim_cur: u64,
+    pub rlim_max: u64,
+}
+
+impl RLimit {
+    pub const INFINITY: Self = Self::new(u64::MAX, u64::MAX);
+
+    pub const fn new(rlim_cur: u64, rlim_max: u64) -> Self {
+        Self { rlim_cur, rlim_max }
+    }
+}
+
+const RLIMIT_NLIMITS: usize = 16;
+
+/// The umask a new process starts with, as set by Linux for `init`.
+const DEFAULT_UMASK: u32 = 0o022;
+
+/// How a task terminated, as reported by `wait4`.
+#[derive(Debug, Clone, Copy)]
+pub enum ExitStatus {
+    /// The task exited by itself with the given code, of which only the low
+    /// 8 bits are kept.
+    Exited(i32),
+    /// The task was killed by the given signal.
+    Signaled(u32),
+}
+
+impl ExitStatus {
+    /// Encodes the status like the `wstatus` of `wait4`.
+    pub fn wait_status(self) -> i32 {
+        match self {
+            Self::Exited(code) => code << 8,
+            Self::Signaled(sig) => (sig & 0x7f) as i32,
+        }
+    }
+
+    /// The exit code of the `axtask`, which is `128 + signal` for a killed
+    /// task like in shells.
+    fn exit_code(self) -> i32 {
+        match self {
+            Self::Exited(code) => code,
+            Self::Signaled(sig) => 128 + sig as i32,
+        }
+    }
+}
+
+/// How an area of the address space was created by `mmap`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MappingRecord {
+    /// Start address of the area.
+    pub start: usize,
+    /// The mapped file, or -1 for anonymous memory.
+    pub fd: i32,
+    /// Offset of the area in the file.
+    pub offset: usize,
+    /// Whether the area was mapped with `MAP_SHARED`.
+    pub shared: bool,
+}
+
+/// The threads of one process, which are torn down together by `exit_group`.
+///
+/// Threads are only created with `CLONE_VM | CLONE_FS`, so the heap and the
+/// filesystem context live here and are shared by every thread.
+pub struct ThreadGroup {
+    /// The live threads of the process.
+    threads: Mutex<Vec<AxTaskRef>>,
+    /// The status the group is torn down with, set by the first `exit_group`
+    /// or fatal signal.
+    exit_status: Mutex<Option<ExitStatus>>,
+    /// Resource limits, indexed by `RLIMIT_*`.
+    rlimits: Mutex<[RLimit; RLIMIT_NLIMITS]>,
+    /// Signal actions, indexed by signal number minus one.
+    sig_actions: Mutex<[SigAction; NSIG as usize]>,
+    /// The areas of the shared address space created by `mmap`.
+    mappings: Mutex<Vec<MappingRecord>>,
+    /// The current program break of the user heap.
+    heap_top: AtomicU64,
+    /// The current working directory.
+    cwd: Mutex<String>,
+    /// The file mode creation mask.
+    umask: AtomicU32,
+    /// Notified when the last thread leaves the group.
+    emptied: WaitQueue,
+}
+
+impl ThreadGroup {
+    pub fn new() -> Self {
+        let mut rlimits = [RLimit::INFINITY; RLIMIT_NLIMITS];
+        rlimits[api::ctypes::RLIMIT_STACK as usize] =
+            RLimit::new(crate::USER_STACK_SIZE as u64, u64::MAX);
+        rlimits[api::ctypes::RLIMIT_NOFILE as usize] =
+            RLimit::new(api::AX_FILE_LIMIT as u64, api::AX_FILE_LIMIT as u64);
+        Self {
+            threads: Mutex::new(Vec::new()),
+            exit_status: Mutex::new(None),
+            rlimits: Mutex::new(rlimits),
+            sig_actions: Mutex::new([SigAction::DEFAULT; NSIG as usize]),
+            mappings: Mutex::new(Vec::new()),
+            heap_top: AtomicU64::new(crate::USER_HEAP_BASE as u64),
+            cwd: Mutex::new(String::from("/")),
+            umask: AtomicU32::new(DEFAULT_UMASK),
+            emptied: WaitQueue::new(),
+        }
+    }
+
+    pub(crate) fn add(&self, task: AxTaskRef) {
+        self.threads.lock().push(task);
+    }
+
+    fn remove(&self, id: u64) {
+        let mut threads = self.threads.lock();
+        threads.retain(|t| t.id().as_u64() != id);
+        if threads.is_empty() {
+            self.emptied.notify_all(false);
+        }
+    }
+
+    /// Blocks until every thread of the group has terminated.
+    pub(crate) fn wait_empty(&self) {
+        self.emptied.wait_until(|| self.threads.lock().is_empty());
+    }
+
+    /// Returns the live threads of the group.
+    pub(crate) fn threads(&self) -> Vec<AxTaskRef> {
+        self.threads.lock().clone()
+    }
+
+    /// Returns the live thread with the given id.
+    pub(crate) fn find(&self, id: u64) -> Option<AxTaskRef> {
+        self.threads.lock().iter().find(|t| t.id().as_u64() == id).cloned()
+    }
+
+    pub(crate) fn rlimit(&self, resource: u32) -> RLimit {
+        self.rlimits.lock()[resource as usize]
+    }
+
+    pub(crate) fn set_rlimit(&self, resource: u32, limit: RLimit) {
+        self.rlimits.lock()[resource as usize] = limit;
+    }
+
+    pub(crate) fn sig_action(&self, sig: u32) -> SigAction {
+        self.sig_actions.lock()[sig as usize - 1]
+    }
+
+    /// Sets the action of `sig` and returns the previous one.
+    pub(crate) fn set_sig_action(&self, sig: u32, action: SigAction) -> SigAction {
+        core::mem::replace(&mut self.sig_actions.lock()[sig as usize - 1], action)
+    }
+
+    pub(crate) fn add_mapping(&self, record: MappingRecord) {
+        self.mappings.lock().push(record);
+    }
+
+    /// Returns the record of the `mmap` area starting at `start`.
+    pub(crate) fn mapping_at(&self, start: usize) -> Option<MappingRecord> {
+        self.mappings.lock().iter().find(|m| m.start == start).copied()
+    }
+
+    /// Follows an `mmap` area that `mremap` moved from `old` to `new`.
+    pub(crate) fn move_mapping(&self, old: usize, new: usize) {
+        if let Some(record) = self.mappings.lock().iter_mut().find(|m| m.start == old) {
+            record.start = new;
+        }
+    }
+
+    pub(crate) fn heap_top(&self) -> u64 {
+        self.heap_top
+            .load(core::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_heap_top(&self, heap_top: u64) {
+        self.heap_top
+            .store(heap_top, core::sync::atomic::Ordering::Relaxed);
+    }
+
+    pub(crate) fn cwd(&self) -> String {
+        self.cwd.lock().clone()
+    }
+
+    pub(crate) fn set_cwd(&self, cwd: String) {
+        *self.cwd.lock() = cwd;
+    }
+
+    pub(crate) fn umask(&self) -> u32 {
+        self.umask.load(core::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Sets the file mode creation mask and returns the previous one.
+    pub(crate) fn set_umask(&self, umask: u32) -> u32 {
+        self.umask
+            .swap(umask, core::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the exit status of the group if it is being torn down.
+    pub(crate) fn exiting(&self) -> Option<ExitStatus> {
+        *self.exit_status.lock()
+    }
+
+    /// Starts tearing down the group, unless it already is.
+    ///
+    /// Returns the status the group exits with, which is the first one given.
+    pub(crate) fn start_exit(&self, status: ExitStatus) -> ExitStatus {
+        *self.exit_status.lock().get_or_insert(status)
+    }
+}
+
+/// The children a task has created, which it can reap with `wait4`.
+pub struct Children {
+    /// Ids of the children that are still running.
+    live: Mutex<Vec<u64>>,
+    /// Children that have exited but not been waited for, with their status.
+    zombies: Mutex<Vec<(u64, ExitStatus)>>,
+    /// Notified whenever a child exits.
+    exited: WaitQueue,
+}
+
+impl Children {
+    pub const fn new() -> Self {
+        Self {
+            live: Mutex::new(Vec::new()),
+            zombies: Mutex::new(Vec::new()),
+            exited: WaitQueue::new(),
+        }
+    }
+
+    pub(crate) fn add(&self, id: u64) {
+        self.live.lock().push(id);
+    }
+
+    /// Turns the child `id` into a zombie and wakes up the waiters.
+    fn report_exit(&self, id: u64, status: ExitStatus) {
+        self.live.lock().retain(|&child| child != id);
+        self.zombies.lock().push((id, status));
+        self.exited.notify_all(false);
+    }
+
+    /// Reaps an exited child matching `id` (any child if `None`), returning
+    /// its id and exit status.
+    ///
+    /// Blocks until one exits unless `nohang` is set. Returns `Ok(None)` if
+    /// `nohang` is set and none has exited yet, `ECHILD` if there is no such
+    /// child at all, or `EINTR` if the waiting thread is killed.
+    pub(crate) fn wait(
+        &self,
+        id: Option<u64>,
+        nohang: bool,
+    ) -> LinuxResult<Option<(u64, ExitStatus)>> {
+        let matches = |child: u64| id.is_none_or(|id| id == child);
+        loop {
+            {
+                let mut zombies = self.zombies.lock();
+                if let Some(pos) = zombies.iter().position(|&(child, _)| matches(child)) {
+                    return Ok(Some(zombies.remove(pos)));
+                }
+            }
+            if !self.live.lock().iter().any(|&child| matches(child)) {
+                return Err(LinuxError::ECHILD);
+            }
+            if nohang {
+                return Ok(None);
+            }
+            self.exited.wait_until(|| {
+                self.zombies.lock().iter().any(|&(child, _)| matches(child))
+                    || !self.live.lock().iter().any(|&child| matches(child))
+                    || crate::signal::interrupted()
+            });
+            if crate::signal::interrupted() {
+                return Err(LinuxError::EINTR);
+            }
+        }
+    }
+}
 
 /// Task extended data for the monolithic kernel.
 pub struct TaskExt {
@@ -19,6 +302,22 @@ pub struct TaskExt {
     ///
     /// When the thread exits, the kernel clears the word at this address if it is not NULL.
     clear_child_tid: AtomicU64,
+    /// CPU time spent in user space, in nanoseconds.
+    utime_ns: AtomicU64,
+    /// CPU time spent in the kernel on behalf of this task, in nanoseconds.
+    stime_ns: AtomicU64,
+    /// When the task last entered or left the kernel, in nanoseconds.
+    last_switch_ns: AtomicU64,
+    /// A fatal signal sent to this task, or 0.
+    pending_kill: AtomicU32,
+    /// The signals blocked by this task.
+    sig_mask: AtomicU64,
+    /// The threads sharing the process with this one.
+    pub thread_group: Arc<ThreadGroup>,
+    /// The children created by this task.
+    pub children: Arc<Children>,
+    /// The children of the creator of this task, to report the exit to.
+    parent: Option<Arc<Children>>,
     /// The user space context.
     pub uctx: UspaceContext,
     /// The virtual memory address space.
@@ -26,15 +325,33 @@ pub struct TaskExt {
 }
 
 impl TaskExt {
-    pub const fn new(uctx: UspaceContext, aspace: Arc<Mutex<AddrSpace>>) -> Self {
+    pub fn new(
+        uctx: UspaceContext,
+        aspace: Arc<Mutex<AddrSpace>>,
+        thread_group: Arc<ThreadGroup>,
+    ) -> Self {
         Self {
             proc_id: 233,
+            thread_group,
+            children: Arc::new(Children::new()),
+            parent: None,
             uctx,
             clear_child_tid: AtomicU64::new(0),
+            utime_ns: AtomicU64::new(0),
+            stime_ns: AtomicU64::new(0),
+            last_switch_ns: AtomicU64::new(axhal::time::monotonic_time_nanos()),
+            pending_kill: AtomicU32::new(0),
+            sig_mask: AtomicU64::new(0),
             aspace,
         }
     }
 
+    /// Makes this task a child that `parent` can wait for.
+    pub(crate) fn with_parent(mut self, parent: Arc<Children>) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
     pub(crate) fn clear_child_tid(&self) -> u64 {
         self.clear_child_tid
             .load(core::sync::atomic::Ordering::Relaxed)
@@ -44,11 +361,129 @@ impl TaskExt {
         self.clear_child_tid
             .store(clear_child_tid, core::sync::atomic::Ordering::Relaxed);
     }
+
+    /// Marks the task to be killed by `sig` on its next return to user space.
+    pub(crate) fn kill(&self, sig: u32) {
+        let _ = self.pending_kill.compare_exchange(
+            0,
+            sig,
+            core::sync::atomic::Ordering::Relaxed,
+            core::sync::atomic::Ordering::Relaxed,
+        );
+    }
+
+    /// Returns the fatal signal sent to this task, if any.
+    pub(crate) fn pending_kill(&self) -> Option<u32> {
+        match self.pending_kill.load(core::sync::atomic::Ordering::Relaxed) {
+            0 => None,
+            sig => Some(sig),
+        }
+    }
+
+    pub(crate) fn sig_mask(&self) -> SigSet {
+        self.sig_mask.load(core::sync::atomic::Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_sig_mask(&self, mask: SigSet) {
+        self.sig_mask
+            .store(mask & !UNCATCHABLE, core::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Charges the time since the last switch to `counter` and starts a new period.
+    fn charge(&self, counter: &AtomicU64) {
+        let now = axhal::time::monotonic_time_nanos();
+        let last = self
+            .last_switch_ns
+            .swap(now, core::sync::atomic::Ordering::Relaxed);
+        counter.fetch_add(now.saturating_sub(last), core::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Called on kernel entry: the time since the last return was spent in user space.
+    pub(crate) fn enter_kernel(&self) {
+        self.charge(&self.utime_ns);
+    }
+
+    /// Called on return to user space: the time since entry was spent in the kernel.
+    pub(crate) fn leave_kernel(&self) {
+        self.charge(&self.stime_ns);
+    }
+
+    /// Returns the user and system CPU time of this task, in nanoseconds.
+    ///
+    /// `axtask` does not account CPU time per task, so this is wall time split at
+    /// syscall boundaries. Time the task spends preempted is charged to whichever
+    /// side it was on, and time blocked in a syscall counts as system time.
+    pub(crate) fn cpu_times(&self) -> (u64, u64) {
+        (
+            self.utime_ns.load(core::sync::atomic::Ordering::Relaxed),
+            self.stime_ns.load(core::sync::atomic::Ordering::Relaxed),
+        )
+    }
 }
 
 axtask::def_task_ext!(TaskExt);
 
-pub fn spawn_user_task(aspace: Arc<Mutex<AddrSpace>>, uctx: UspaceContext) -> AxTaskRef {
+/// Terminates the current thread only.
+///
+/// Only the low 8 bits of `exit_code` are kept, as in POSIX.
+pub(crate) fn exit_thread(exit_code: i32) -> ! {
+    terminate(ExitStatus::Exited(exit_code & 0xff))
+}
+
+/// Terminates the current thread with the given status.
+///
+/// If the thread registered a `clear_child_tid` address, zero is written there
+/// and one futex waiter on it is woken, which is what `pthread_join` waits for.
+pub(crate) fn terminate(status: ExitStatus) -> ! {
+    let curr = axtask::current();
+    let clear_child_tid = curr.task_ext().clear_child_tid() as usize;
+    if clear_child_tid != 0
+        && crate::syscall::copy_to_user(&curr.task_ext().aspace.lock(), clear_child_tid, &0u32).is_ok()
+    {
+        crate::futex::futex_wake(clear_child_tid, 1);
+    }
+    curr.task_ext().thread_group.remove(curr.id().as_u64());
+    if let Some(parent) = &curr.task_ext().parent {
+        parent.report_exit(curr.id().as_u64(), status);
+    }
+    axtask::exit(status.exit_code())
+}
+
+/// Wakes `task` from any blocking wait, so that it notices it was killed.
+///
+/// The waits re-check [`crate::signal::interrupted`] when woken, and fail
+/// with `EINTR` if it holds.
+pub(crate) fn interrupt(task: &AxTaskRef) {
+    task.task_ext().children.exited.notify_all(false);
+    crate::futex::interrupt_waiters();
+}
+
+/// Terminates every thread of the current process.
+///
+/// There is no way to stop another task from the outside, so the siblings are
+/// marked and woken from blocking waits here. Each exits itself the next time
+/// it would return to user space, including from a timer interrupt while it
+/// is running user code.
+pub(crate) fn exit_group(exit_code: i32) -> ! {
+    let curr = axtask::current();
+    let group = &curr.task_ext().thread_group;
+    let status = group.start_exit(ExitStatus::Exited(exit_code & 0xff));
+    let siblings = group.threads();
+    debug!(
+        "exit_group({:?}): {} thread(s) in the group",
+        status,
+        siblings.len()
+    );
+    for task in siblings.iter().filter(|t| t.id() != curr.id()) {
+        interrupt(task);
+    }
+    terminate(status)
+}
+
+/// Creates a user task that enters user space with the context in `ext`.
+///
+/// The task is not spawned yet, so that the caller can publish its id first.
+pub(crate) fn new_user_task(ext: TaskExt, name: String) -> TaskInner {
     let mut task = TaskInner::new(
         || {
             let curr = axtask::current();
@@ -61,11 +496,24 @@ pub fn spawn_user_task(aspace: Arc<Mutex<AddrSpace>>, uctx: UspaceContext) -> Ax
             );
             unsafe { curr.task_ext().uctx.enter_uspace(kstack_top) };
         },
-        "userboot".into(),
+        name,
         crate::KERNEL_STACK_SIZE,
     );
     task.ctx_mut()
-        .set_page_table_root(aspace.lock().page_table_root());
-    task.init_task_ext(TaskExt::new(uctx, aspace));
-    axtask::spawn_task(task)
+        .set_page_table_root(ext.aspace.lock().page_table_root());
+    task.init_task_ext(ext);
+    task
+}
+
+/// Spawns a task made by [`new_user_task`] and adds it to its thread group.
+pub(crate) fn spawn_user_thread(task: TaskInner) -> AxTaskRef {
+    let task = axtask::spawn_task(task);
+    task.task_ext().thread_group.add(task.clone());
+    task
+}
+
+pub fn spawn_user_task(aspace: Arc<Mutex<AddrSpace>>, uctx: UspaceContext) -> AxTaskRef {
+    let thread_group = Arc::new(ThreadGroup::new());
+    let ext = TaskExt::new(uctx, aspace, thread_group);
+    spawn_user_thread(new_user_task(ext, "userboot".into()))
 }
@@ -16,6 +16,7 @@ mod page;
 use allocator::{AllocResult, BaseAllocator, BitmapPageAllocator, ByteAllocator, PageAllocator};
 use core::alloc::{GlobalAlloc, Layout};
 use core::ptr::NonNull;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use kspin::SpinNoIrq;
 
 const PAGE_SIZE: usize = 0x1000;
@@ -36,6 +37,21 @@ cfg_if::cfg_if! {
     }
 }
 
+/// A snapshot of the memory usage of the [`GlobalAllocator`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllocStats {
+    /// Bytes currently handed out, by [`alloc`] and [`alloc_pages`] together.
+    ///
+    /// [`alloc`]: GlobalAllocator::alloc
+    /// [`alloc_pages`]: GlobalAllocator::alloc_pages
+    pub used_bytes: usize,
+    /// Bytes that can still be allocated, as free pages plus the free space
+    /// of the byte allocator.
+    pub free_bytes: usize,
+    /// The largest value `used_bytes` has reached.
+    pub peak_bytes: usize,
+}
+
 /// The global allocator used by ArceOS.
 ///
 /// It combines a [`ByteAllocator`] and a [`PageAllocator`] into a simple
@@ -50,6 +66,8 @@ cfg_if::cfg_if! {
 pub struct GlobalAllocator {
     balloc: SpinNoIrq<DefaultByteAllocator>,
     palloc: SpinNoIrq<BitmapPageAllocator<PAGE_SIZE>>,
+    used: AtomicUsize,
+    peak: AtomicUsize,
 }
 
 impl GlobalAllocator {
@@ -58,6 +76,8 @@ impl GlobalAllocator {
         Self {
             balloc: SpinNoIrq::new(DefaultByteAllocator::new()),
             palloc: SpinNoIrq::new(BitmapPageAllocator::new()),
+            used: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
         }
     }
 
@@ -84,6 +104,8 @@ impl GlobalAllocator {
         let init_heap_size = MIN_HEAP_SIZE;
         self.palloc.lock().init(start_vaddr, size);
         let heap_ptr = self
+            .palloc
+            .lock()
             .alloc_pages(init_heap_size / PAGE_SIZE, PAGE_SIZE)
             .unwrap();
         self.balloc.lock().init(heap_ptr, init_heap_size);
@@ -107,6 +129,7 @@ impl GlobalAllocator {
         let mut balloc = self.balloc.lock();
         loop {
             if let Ok(ptr) = balloc.alloc(layout) {
+                self.account_alloc(layout.size());
                 return Ok(ptr);
             } else {
                 let old_size = balloc.total_bytes();
@@ -114,7 +137,11 @@ impl GlobalAllocator {
                     .max(layout.size())
                     .next_power_of_two()
                     .max(PAGE_SIZE);
-                let heap_ptr = self.alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
+                // Heap pages are not counted as used, only the bytes allocated from them.
+                let heap_ptr = self
+                    .palloc
+                    .lock()
+                    .alloc_pages(expand_size / PAGE_SIZE, PAGE_SIZE)?;
                 debug!(
                     "expand heap memory: [{:#x}, {:#x})",
                     heap_ptr,
@@ -133,7 +160,8 @@ impl GlobalAllocator {
     ///
     /// [`alloc`]: GlobalAllocator::alloc
     pub fn dealloc(&self, pos: NonNull<u8>, layout: Layout) {
-        self.balloc.lock().dealloc(pos, layout)
+        self.balloc.lock().dealloc(pos, layout);
+        self.used.fetch_sub(layout.size(), Ordering::Relaxed);
     }
 
     /// Allocates contiguous pages.
@@ -143,7 +171,9 @@ impl GlobalAllocator {
     /// `align_pow2` must be a power of 2, and the returned region bound will be
     /// aligned to it.
     pub fn alloc_pages(&self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
-        self.palloc.lock().alloc_pages(num_pages, align_pow2)
+        let pos = self.palloc.lock().alloc_pages(num_pages, align_pow2)?;
+        self.account_alloc(num_pages * PAGE_SIZE);
+        Ok(pos)
     }
 
     /// Gives back the allocated pages starts from `pos` to the page allocator.
@@ -154,7 +184,8 @@ impl GlobalAllocator {
     ///
     /// [`alloc_pages`]: GlobalAllocator::alloc_pages
     pub fn dealloc_pages(&self, pos: usize, num_pages: usize) {
-        self.palloc.lock().dealloc_pages(pos, num_pages)
+        self.palloc.lock().dealloc_pages(pos, num_pages);
+        self.used.fetch_sub(num_pages * PAGE_SIZE, Ordering::Relaxed);
     }
 
     /// Returns the number of allocated bytes in the byte allocator.
@@ -176,6 +207,21 @@ impl GlobalAllocator {
     pub fn available_pages(&self) -> usize {
         self.palloc.lock().available_pages()
     }
+
+    /// Returns the used, free and peak bytes of the allocator.
+    pub fn stats(&self) -> AllocStats {
+        let free_heap = self.balloc.lock().available_bytes();
+        AllocStats {
+            used_bytes: self.used.load(Ordering::Relaxed),
+            free_bytes: self.available_pages() * PAGE_SIZE + free_heap,
+            peak_bytes: self.peak.load(Ordering::Relaxed),
+        }
+    }
+
+    fn account_alloc(&self, size: usize) {
+        let used = self.used.fetch_add(size, Ordering::Relaxed) + size;
+        self.peak.fetch_max(used, Ordering::Relaxed);
+    }
 }
 
 unsafe impl GlobalAlloc for GlobalAllocator {
@@ -9,6 +9,21 @@ pub use self::file::{File, FileType, Metadata, OpenOptions, Permissions};
 use alloc::{string::String, vec::Vec};
 use axio::{self as io, prelude::*};
 
+/// Statistics of a mounted filesystem, as reported by `statfs`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileSystemStat {
+    /// Type of the filesystem, as a Linux magic number (e.g. `TMPFS_MAGIC`).
+    pub magic: u64,
+    /// Size of a block in bytes.
+    pub block_size: u64,
+    /// Total number of blocks, or 0 if the filesystem is not size-limited.
+    pub blocks: u64,
+    /// Number of free blocks.
+    pub blocks_free: u64,
+    /// Maximum length of a file name.
+    pub name_max: u64,
+}
+
 /// Returns an iterator over the entries within a directory.
 pub fn read_dir(path: &str) -> io::Result<ReadDir> {
     ReadDir::new(path)
@@ -80,6 +95,11 @@ pub fn remove_file(path: &str) -> io::Result<()> {
     crate::root::remove_file(None, path)
 }
 
+/// Returns the statistics of the filesystem containing the given path.
+pub fn statfs(path: &str) -> io::Result<FileSystemStat> {
+    crate::root::statfs(path)
+}
+
 /// Rename a file or directory to a new name.
 /// Delete the original file if `old` already exists.
 ///
@@ -6,9 +6,11 @@ use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps};
 use axsync::Mutex;
 use fatfs::{Dir, File, LossyOemCpConverter, NullTimeProvider, Read, Seek, SeekFrom, Write};
 
+use crate::api::FileSystemStat;
 use crate::dev::Disk;
 
 const BLOCK_SIZE: usize = 512;
+const MSDOS_SUPER_MAGIC: u64 = 0x4d44;
 
 pub struct FatFileSystem {
     inner: fatfs::FileSystem<Disk, NullTimeProvider, LossyOemCpConverter>,
@@ -53,6 +55,22 @@ impl FatFileSystem {
         unsafe { *self.root_dir.get() = Some(Self::new_dir(self.inner.root_dir())) }
     }
 
+    /// Returns the filesystem statistics, counting in clusters.
+    pub fn stat(&self) -> FileSystemStat {
+        let mut stat = FileSystemStat {
+            magic: MSDOS_SUPER_MAGIC,
+            block_size: BLOCK_SIZE as u64,
+            name_max: 255,
+            ..Default::default()
+        };
+        if let Ok(stats) = self.inner.stats() {
+            stat.block_size = stats.cluster_size() as u64;
+            stat.blocks = stats.total_clusters() as u64;
+            stat.blocks_free = stats.free_clusters() as u64;
+        }
+        stat
+    }
+
     fn new_file(file: File<'_, Disk, NullTimeProvider, LossyOemCpConverter>) -> Arc<FileWrapper> {
         Arc::new(FileWrapper(Mutex::new(file)))
     }
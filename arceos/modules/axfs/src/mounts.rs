@@ -1,8 +1,36 @@
 use alloc::sync::Arc;
 use axfs_vfs::{VfsNodeType, VfsOps, VfsResult};
 
+use crate::api::FileSystemStat;
 use crate::fs;
 
+// Linux `statfs` magic numbers of the filesystems below.
+const TMPFS_MAGIC: u64 = 0x0102_1994;
+const PROC_SUPER_MAGIC: u64 = 0x9fa0;
+const SYSFS_MAGIC: u64 = 0x6265_6572;
+
+/// Statistics of an in-memory filesystem of the given type, which only
+/// grows as long as there is free memory.
+const fn memory_fs_stat(magic: u64) -> FileSystemStat {
+    FileSystemStat {
+        magic,
+        block_size: 4096,
+        blocks: 0,
+        blocks_free: 0,
+        name_max: 255,
+    }
+}
+
+/// Statistics of a filesystem we know nothing about.
+#[cfg(feature = "myfs")]
+pub(crate) fn unknown_stat() -> FileSystemStat {
+    FileSystemStat {
+        block_size: 512,
+        name_max: 255,
+        ..Default::default()
+    }
+}
+
 #[cfg(feature = "devfs")]
 pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     let null = fs::devfs::NullDev;
@@ -16,11 +44,27 @@ pub(crate) fn devfs() -> Arc<fs::devfs::DeviceFileSystem> {
     Arc::new(devfs)
 }
 
+/// Like Linux, `devtmpfs` is reported as `tmpfs`.
+#[cfg(feature = "devfs")]
+pub(crate) fn devfs_stat() -> FileSystemStat {
+    memory_fs_stat(TMPFS_MAGIC)
+}
+
+#[cfg(feature = "ramfs")]
+pub(crate) fn ramfs_stat() -> FileSystemStat {
+    memory_fs_stat(TMPFS_MAGIC)
+}
+
 #[cfg(feature = "ramfs")]
 pub(crate) fn ramfs() -> Arc<fs::ramfs::RamFileSystem> {
     Arc::new(fs::ramfs::RamFileSystem::new())
 }
 
+#[cfg(feature = "procfs")]
+pub(crate) fn procfs_stat() -> FileSystemStat {
+    memory_fs_stat(PROC_SUPER_MAGIC)
+}
+
 #[cfg(feature = "procfs")]
 pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     let procfs = fs::ramfs::RamFileSystem::new();
@@ -47,6 +91,11 @@ pub(crate) fn procfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     Ok(Arc::new(procfs))
 }
 
+#[cfg(feature = "sysfs")]
+pub(crate) fn sysfs_stat() -> FileSystemStat {
+    memory_fs_stat(SYSFS_MAGIC)
+}
+
 #[cfg(feature = "sysfs")]
 pub(crate) fn sysfs() -> VfsResult<Arc<fs::ramfs::RamFileSystem>> {
     let sysfs = fs::ramfs::RamFileSystem::new();
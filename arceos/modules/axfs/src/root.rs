@@ -8,26 +8,31 @@ use axfs_vfs::{VfsNodeAttr, VfsNodeOps, VfsNodeRef, VfsNodeType, VfsOps, VfsResu
 use axsync::Mutex;
 use lazyinit::LazyInit;
 
-use crate::{api::FileType, fs, mounts};
+use crate::{api::FileSystemStat, api::FileType, fs, mounts};
 
 static CURRENT_DIR_PATH: Mutex<String> = Mutex::new(String::new());
 static CURRENT_DIR: LazyInit<Mutex<VfsNodeRef>> = LazyInit::new();
 
+/// Returns the current statistics of a mounted filesystem.
+type StatFn = fn() -> FileSystemStat;
+
 struct MountPoint {
     path: &'static str,
     fs: Arc<dyn VfsOps>,
+    stat: StatFn,
 }
 
 struct RootDirectory {
     main_fs: Arc<dyn VfsOps>,
+    main_stat: StatFn,
     mounts: Vec<MountPoint>,
 }
 
 static ROOT_DIR: LazyInit<Arc<RootDirectory>> = LazyInit::new();
 
 impl MountPoint {
-    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>) -> Self {
-        Self { path, fs }
+    pub fn new(path: &'static str, fs: Arc<dyn VfsOps>, stat: StatFn) -> Self {
+        Self { path, fs, stat }
     }
 }
 
@@ -38,14 +43,15 @@ impl Drop for MountPoint {
 }
 
 impl RootDirectory {
-    pub const fn new(main_fs: Arc<dyn VfsOps>) -> Self {
+    pub const fn new(main_fs: Arc<dyn VfsOps>, main_stat: StatFn) -> Self {
         Self {
             main_fs,
+            main_stat,
             mounts: Vec::new(),
         }
     }
 
-    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>) -> AxResult {
+    pub fn mount(&mut self, path: &'static str, fs: Arc<dyn VfsOps>, stat: StatFn) -> AxResult {
         if path == "/" {
             return ax_err!(InvalidInput, "cannot mount root filesystem");
         }
@@ -58,7 +64,7 @@ impl RootDirectory {
         // create the mount point in the main filesystem if it does not exist
         self.main_fs.root_dir().create(path, FileType::Dir)?;
         fs.mount(path, self.main_fs.root_dir().lookup(path)?)?;
-        self.mounts.push(MountPoint::new(path, fs));
+        self.mounts.push(MountPoint::new(path, fs, stat));
         Ok(())
     }
 
@@ -70,6 +76,17 @@ impl RootDirectory {
         self.mounts.iter().any(|mp| mp.path == path)
     }
 
+    /// Returns the statistics of the filesystem that `path` (absolute) is on.
+    fn mounted_stat(&self, path: &str) -> FileSystemStat {
+        let path = path.trim_matches('/');
+        // Same longest-prefix match as `lookup_mounted_fs`.
+        self.mounts
+            .iter()
+            .filter(|mp| path.starts_with(&mp.path[1..]))
+            .max_by_key(|mp| mp.path.len())
+            .map_or_else(self.main_stat, |mp| (mp.stat)())
+    }
+
     fn lookup_mounted_fs<F, T>(&self, path: &str, f: F) -> AxResult<T>
     where
         F: FnOnce(Arc<dyn VfsOps>, &str) -> AxResult<T>,
@@ -147,36 +164,38 @@ pub(crate) fn init_rootfs(disk: crate::dev::Disk) {
     cfg_if::cfg_if! {
         if #[cfg(feature = "myfs")] { // override the default filesystem
             let main_fs = fs::myfs::new_myfs(disk);
+            let main_stat: StatFn = mounts::unknown_stat;
         } else if #[cfg(feature = "fatfs")] {
             static FAT_FS: LazyInit<Arc<fs::fatfs::FatFileSystem>> = LazyInit::new();
             FAT_FS.init_once(Arc::new(fs::fatfs::FatFileSystem::new(disk)));
             FAT_FS.init();
             let main_fs = FAT_FS.clone();
+            let main_stat: StatFn = || FAT_FS.stat();
         }
     }
 
-    let mut root_dir = RootDirectory::new(main_fs);
+    let mut root_dir = RootDirectory::new(main_fs, main_stat);
 
     #[cfg(feature = "devfs")]
     root_dir
-        .mount("/dev", mounts::devfs())
+        .mount("/dev", mounts::devfs(), mounts::devfs_stat)
         .expect("failed to mount devfs at /dev");
 
     #[cfg(feature = "ramfs")]
     root_dir
-        .mount("/tmp", mounts::ramfs())
+        .mount("/tmp", mounts::ramfs(), mounts::ramfs_stat)
         .expect("failed to mount ramfs at /tmp");
 
     // Mount another ramfs as procfs
     #[cfg(feature = "procfs")]
     root_dir // should not fail
-        .mount("/proc", mounts::procfs().unwrap())
+        .mount("/proc", mounts::procfs().unwrap(), mounts::procfs_stat)
         .expect("fail to mount procfs at /proc");
 
     // Mount another ramfs as sysfs
     #[cfg(feature = "sysfs")]
     root_dir // should not fail
-        .mount("/sys", mounts::sysfs().unwrap())
+        .mount("/sys", mounts::sysfs().unwrap(), mounts::sysfs_stat)
         .expect("fail to mount sysfs at /sys");
 
     ROOT_DIR.init_once(Arc::new(root_dir));
@@ -201,6 +220,11 @@ pub(crate) fn absolute_path(path: &str) -> AxResult<String> {
     }
 }
 
+pub(crate) fn statfs(path: &str) -> AxResult<FileSystemStat> {
+    lookup(None, path)?;
+    Ok(ROOT_DIR.mounted_stat(&absolute_path(path)?))
+}
+
 pub(crate) fn lookup(dir: Option<&VfsNodeRef>, path: &str) -> AxResult<VfsNodeRef> {
     if path.is_empty() {
         return ax_err!(NotFound);
@@ -22,6 +22,11 @@ pub static PAGE_FAULT: [fn(VirtAddr, MappingFlags, bool) -> bool];
 #[def_trap_handler]
 pub static SYSCALL: [fn(&TrapFrame, usize) -> isize];
 
+/// A slice of functions called before returning to user space from any trap.
+#[cfg(feature = "uspace")]
+#[def_trap_handler]
+pub static RETURN_TO_USER: [fn(&TrapFrame)];
+
 #[allow(unused_macros)]
 macro_rules! handle_trap {
     ($trap:ident, $($args:tt)*) => {{
@@ -43,3 +48,13 @@ macro_rules! handle_trap {
 pub(crate) fn handle_syscall(tf: &TrapFrame, syscall_num: usize) -> isize {
     SYSCALL[0](tf, syscall_num)
 }
+
+/// Call the external handlers to run before returning to user space.
+///
+/// Unlike the other traps, having no handler here is fine.
+#[cfg(feature = "uspace")]
+pub(crate) fn handle_return_to_user(tf: &TrapFrame) {
+    for handler in RETURN_TO_USER.iter() {
+        handler(tf);
+    }
+}
@@ -12,6 +12,7 @@ use memory_set::{MemoryArea, MemorySet};
 use crate::backend::Backend;
 use crate::paging_err_to_ax_err;
 use crate::mapping_err_to_ax_err;
+use alloc::collections::BTreeSet;
 use alloc::vec::Vec;
 
 /// The virtual memory address space.
@@ -19,6 +20,8 @@ pub struct AddrSpace {
     va_range: VirtAddrRange,
     areas: MemorySet<Backend>,
     pt: PageTable,
+    /// Start addresses of the pages locked in memory by `mlock`.
+    locked: BTreeSet<VirtAddr>,
 }
 
 impl AddrSpace {
@@ -59,6 +62,7 @@ impl AddrSpace {
             va_range: VirtAddrRange::from_start_size(base, size),
             areas: MemorySet::new(),
             pt: PageTable::try_new().map_err(|_| AxError::NoMemory)?,
+            locked: BTreeSet::new(),
         })
     }
 
@@ -77,6 +81,50 @@ impl AddrSpace {
         Ok(())
     }
 
+    /// Checks if the given address range is fully covered by mapped areas.
+    pub fn is_mapped(&self, start: VirtAddr, size: usize) -> bool {
+        let end = start + size;
+        let mut vaddr = start;
+        while vaddr < end {
+            match self.areas.find(vaddr) {
+                Some(area) => vaddr = area.end(),
+                None => return false,
+            }
+        }
+        true
+    }
+
+    /// Checks if the given address range is fully covered by mapped areas
+    /// whose flags all contain `access_flags`.
+    pub fn can_access_range(&self, start: VirtAddr, size: usize, access_flags: MappingFlags) -> bool {
+        let end = start + size;
+        let mut vaddr = start;
+        while vaddr < end {
+            match self.areas.find(vaddr) {
+                Some(area) if area.flags().contains(access_flags) => vaddr = area.end(),
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Checks if the given address range overlaps with any mapped area.
+    pub fn overlaps(&self, start: VirtAddr, size: usize) -> bool {
+        self.areas
+            .overlaps(VirtAddrRange::from_start_size(start, size))
+    }
+
+    /// Returns the mapping flags of the area containing the given address.
+    pub fn area_flags(&self, vaddr: VirtAddr) -> Option<MappingFlags> {
+        self.areas.find(vaddr).map(|area| area.flags())
+    }
+
+    /// Returns an iterator over the address ranges and flags of the mapped
+    /// areas, in ascending order of address.
+    pub fn areas(&self) -> impl Iterator<Item = (VirtAddrRange, MappingFlags)> + '_ {
+        self.areas.iter().map(|area| (area.va_range(), area.flags()))
+    }
+
     /// Finds a free area that can accommodate the given size.
     ///
     /// The search starts from the given hint address, and the area should be within the given limit range.
@@ -134,6 +182,9 @@ impl AddrSpace {
     /// See [`Backend`] for more details about the mapping backends.
     ///
     /// The `flags` parameter indicates the mapping permissions and attributes.
+    /// The frames are zeroed when they are allocated, up front if `populate`
+    /// is set or on the first access otherwise, so the area always reads as
+    /// zeros before it is written.
     ///
     /// Returns an error if the address range is out of the address space or not
     /// aligned.
@@ -170,13 +221,51 @@ impl AddrSpace {
             return ax_err!(InvalidInput, "address not aligned");
         }
 
-        self.pt
-            .unmap_region(start, size, true)
-            .map_err(paging_err_to_ax_err)?
-            .ignore();
+        self.areas
+            .unmap(start, size, &mut self.pt)
+            .map_err(mapping_err_to_ax_err)?;
+        self.locked.retain(|&page| page < start || page >= start + size);
         Ok(())
     }
 
+    /// Marks the pages within the specified range as locked in memory, so
+    /// that they must never be evicted.
+    ///
+    /// Returns an error if the address range is not aligned, or not fully
+    /// covered by mapped areas.
+    pub fn lock(&mut self, start: VirtAddr, size: usize) -> AxResult {
+        if !start.is_aligned_4k() || !is_aligned_4k(size) {
+            return ax_err!(InvalidInput, "address not aligned");
+        }
+        if !self.is_mapped(start, size) {
+            return ax_err!(NoMemory, "address not mapped");
+        }
+        for page in PageIter4K::new(start, start + size).unwrap() {
+            self.locked.insert(page);
+        }
+        Ok(())
+    }
+
+    /// Unlocks the pages within the specified range.
+    ///
+    /// Returns an error if the address range is not aligned, or not fully
+    /// covered by mapped areas.
+    pub fn unlock(&mut self, start: VirtAddr, size: usize) -> AxResult {
+        if !start.is_aligned_4k() || !is_aligned_4k(size) {
+            return ax_err!(InvalidInput, "address not aligned");
+        }
+        if !self.is_mapped(start, size) {
+            return ax_err!(NoMemory, "address not mapped");
+        }
+        self.locked.retain(|&page| page < start || page >= start + size);
+        Ok(())
+    }
+
+    /// Checks if the page containing the given address is locked in memory.
+    pub fn is_locked(&self, vaddr: VirtAddr) -> bool {
+        self.locked.contains(&vaddr.align_down_4k())
+    }
+
     /// To process data in this area with the given function.
     ///
     /// Now it supports reading and writing data in the given interval.
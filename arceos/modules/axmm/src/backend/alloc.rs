@@ -43,12 +43,14 @@ impl Backend {
         if populate {
             // allocate all possible physical frames for populated mapping.
             for addr in PageIter4K::new(start, start + size).unwrap() {
-                if let Some(frame) = alloc_frame(true) {
-                    if let Ok(tlb) = pt.map(addr, frame, PageSize::Size4K, flags) {
-                        tlb.ignore(); // TLB flush on map is unnecessary, as there are no outdated mappings.
-                    } else {
-                        return false;
-                    }
+                // Leaving a page out would report success for a partly mapped area.
+                let Some(frame) = alloc_frame(true) else {
+                    return false;
+                };
+                if let Ok(tlb) = pt.map(addr, frame, PageSize::Size4K, flags) {
+                    tlb.ignore(); // TLB flush on map is unnecessary, as there are no outdated mappings.
+                } else {
+                    return false;
                 }
             }
             true
@@ -32,6 +32,37 @@ fn test_sched_fifo() {
     }
 }
 
+#[test]
+fn test_yield_ping_pong() {
+    let _lock = SERIAL.lock();
+    INIT.call_once(axtask::init_scheduler);
+
+    const NUM_ROUNDS: usize = 100;
+    static PING: AtomicUsize = AtomicUsize::new(0);
+    static PONG: AtomicUsize = AtomicUsize::new(0);
+
+    let ping = axtask::spawn(|| {
+        for _ in 0..NUM_ROUNDS {
+            PING.fetch_add(1, Ordering::Relaxed);
+            axtask::yield_now();
+            // the other task must have made progress while we yielded.
+            assert!(PONG.load(Ordering::Relaxed) + 1 >= PING.load(Ordering::Relaxed));
+        }
+    });
+    let pong = axtask::spawn(|| {
+        for _ in 0..NUM_ROUNDS {
+            PONG.fetch_add(1, Ordering::Relaxed);
+            axtask::yield_now();
+            assert!(PING.load(Ordering::Relaxed) + 1 >= PONG.load(Ordering::Relaxed));
+        }
+    });
+
+    ping.join();
+    pong.join();
+    assert_eq!(PING.load(Ordering::Relaxed), NUM_ROUNDS);
+    assert_eq!(PONG.load(Ordering::Relaxed), NUM_ROUNDS);
+}
+
 #[test]
 fn test_fp_state_switch() {
     let _lock = SERIAL.lock();
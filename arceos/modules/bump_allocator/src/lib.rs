@@ -1,6 +1,11 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
 use allocator::{BaseAllocator, ByteAllocator, PageAllocator};
+use core::alloc::{GlobalAlloc, Layout};
+use kspin::SpinNoIrq;
+
+#[cfg(test)]
+mod tests;
 
 /// Early memory allocator
 /// Use it before formal bytes-allocator and pages-allocator can work!
@@ -14,17 +19,34 @@ use allocator::{BaseAllocator, ByteAllocator, PageAllocator};
 ///
 /// For bytes area, 'count' records number of allocations.
 /// When it goes down to ZERO, free bytes-used area.
-/// For pages area, it will never be freed!
+/// The most recent allocation is also freed right away, as a stack.
+/// For pages area, only the last allocated block can be freed, as a stack.
+/// 'page_count' records the number of live page allocations.
+///
+/// `PAGE` is the granularity of the pages area. The arena size is given at
+/// runtime by `init`, and the bytes area does not depend on `PAGE`.
 ///
-pub struct EarlyAllocator<const SIZE: usize> {
+/// Once the formal allocator is up, [`EarlyAllocator::drain`] hands the
+/// avail-area over to it, and the early allocator stops allocating.
+///
+pub struct EarlyAllocator<const PAGE: usize> {
     start: usize,
     size: usize,
     b_pos: usize,
     p_pos: usize,
     count: usize,
+    page_count: usize,
+    peak_bytes: usize,
+    drained: bool,
 }
 
-impl<const SIZE: usize> EarlyAllocator<SIZE> {
+impl<const PAGE: usize> EarlyAllocator<PAGE> {
+    /// Creates an empty allocator, to be initialized with an arena later.
+    ///
+    /// The arena size is not part of the type, so [`total_bytes`] is zero
+    /// until then. Size the backing buffer with a constant of its own.
+    ///
+    /// [`total_bytes`]: ByteAllocator::total_bytes
     pub const fn new() -> Self {
         Self {
             start: 0,
@@ -32,60 +54,318 @@ impl<const SIZE: usize> EarlyAllocator<SIZE> {
             b_pos: 0,
             p_pos: 0,
             count: 0,
+            page_count: 0,
+            peak_bytes: 0,
+            drained: false,
         }
     }
-}
 
-impl<const SIZE: usize> BaseAllocator for EarlyAllocator<SIZE> {
-    fn init(&mut self, start: usize, size: usize) {
+    /// Initializes the allocator with `[start, start + size)`.
+    ///
+    /// Fails with `InvalidParam` if the arena is empty or wraps around the
+    /// address space.
+    pub fn try_init(&mut self, start: usize, size: usize) -> allocator::AllocResult {
+        if size == 0 || start.checked_add(size).is_none() {
+            return Err(allocator::AllocError::InvalidParam);
+        }
         self.start = start;
         self.size = size;
-        self.b_pos = start;
-        self.p_pos = start + size;
+        self.reset();
+        Ok(())
+    }
+
+    /// Frees everything, as right after [`init`] with the same region.
+    ///
+    /// It does nothing harmful on an allocator that was never initialized.
+    ///
+    /// [`init`]: BaseAllocator::init
+    pub fn reset(&mut self) {
+        self.b_pos = self.start;
+        self.p_pos = self.start + self.size;
         self.count = 0;
+        self.page_count = 0;
+        self.peak_bytes = 0;
+        self.drained = false;
+    }
+
+    /// Returns the number of live byte allocations.
+    pub fn alloc_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of live byte allocations, same as [`alloc_count`].
+    ///
+    /// [`alloc_count`]: EarlyAllocator::alloc_count
+    pub fn byte_alloc_count(&self) -> usize {
+        self.count
+    }
+
+    /// Returns the number of live page allocations.
+    ///
+    /// Frees other than of the last block are ignored and not counted.
+    pub fn page_alloc_count(&self) -> usize {
+        self.page_count
+    }
+
+    /// Allocates bytes like [`ByteAllocator::alloc`], and zero-fills them.
+    ///
+    /// The arena must be writable memory, unlike for the other methods.
+    pub fn alloc_zeroed(
+        &mut self,
+        layout: Layout,
+    ) -> allocator::AllocResult<core::ptr::NonNull<u8>> {
+        let ptr = self.alloc(layout)?;
+        // SAFETY: `alloc` has checked that the region lies within the arena.
+        unsafe { core::ptr::write_bytes(ptr.as_ptr(), 0, layout.size()) };
+        Ok(ptr)
+    }
+
+    /// Shrinks the bytes at `ptr` from `old_layout` to `new_layout` in place.
+    ///
+    /// The tail is freed if this is the most recent allocation, otherwise the
+    /// block just keeps its old size. Either way `ptr` is returned.
+    pub fn shrink(
+        &mut self,
+        ptr: core::ptr::NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> allocator::AllocResult<core::ptr::NonNull<u8>> {
+        if new_layout.size() > old_layout.size() {
+            return Err(allocator::AllocError::InvalidParam);
+        }
+        let pos = ptr.as_ptr() as usize;
+        if pos % new_layout.align() != 0 {
+            return Err(allocator::AllocError::NoMemory);
+        }
+        if !self.drained && pos + old_layout.size() == self.b_pos {
+            self.b_pos = pos + new_layout.size();
+        }
+        Ok(ptr)
+    }
+
+    /// Grows the bytes at `ptr` from `old_layout` to `new_layout` in place.
+    ///
+    /// Only the most recent allocation can grow, and only into the
+    /// avail-area. Otherwise it fails with `NoMemory`, and the caller should
+    /// allocate a new block and copy.
+    pub fn grow(
+        &mut self,
+        ptr: core::ptr::NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> allocator::AllocResult<core::ptr::NonNull<u8>> {
+        if new_layout.size() < old_layout.size() {
+            return Err(allocator::AllocError::InvalidParam);
+        }
+        let pos = ptr.as_ptr() as usize;
+        if self.drained
+            || pos % new_layout.align() != 0
+            || pos + old_layout.size() != self.b_pos
+        {
+            return Err(allocator::AllocError::NoMemory);
+        }
+        let end = pos
+            .checked_add(new_layout.size())
+            .ok_or(allocator::AllocError::NoMemory)?;
+        self.check_free(self.b_pos, end)?;
+        self.b_pos = end;
+        self.peak_bytes = self.peak_bytes.max(end - self.start);
+        debug_assert!(self.invariants_hold());
+        Ok(ptr)
+    }
+
+    /// Returns the highest [`used_bytes`] since the last `init` or `reset`.
+    ///
+    /// [`used_bytes`]: ByteAllocator::used_bytes
+    pub fn peak_used_bytes(&self) -> usize {
+        self.peak_bytes
+    }
+
+    /// Checks `start <= b_pos <= p_pos <= end`, and that the bytes and pages
+    /// views of the avail-area agree.
+    pub fn invariants_hold(&self) -> bool {
+        let ordered = self.start <= self.b_pos
+            && self.b_pos <= self.p_pos
+            && self.p_pos <= self.start + self.size;
+        ordered
+            && self.available_bytes() == self.p_pos - self.b_pos
+            && self.available_pages() == self.available_bytes() / PAGE
+    }
+
+    /// Returns the size of the avail-area between the two cursors.
+    fn region_free(&self) -> usize {
+        self.p_pos.saturating_sub(self.b_pos)
+    }
+
+    /// Checks that `[lo, hi)` lies within the avail-area, so that neither
+    /// allocation path lets the bytes and pages cursors cross.
+    fn check_free(&self, lo: usize, hi: usize) -> allocator::AllocResult {
+        if self.b_pos <= lo && lo <= hi && hi <= self.p_pos {
+            Ok(())
+        } else {
+            Err(allocator::AllocError::NoMemory)
+        }
+    }
+
+    /// Returns the address where the next bytes are allocated, `b_pos`.
+    pub fn byte_cursor(&self) -> usize {
+        self.b_pos
+    }
+
+    /// Returns the lowest address of the allocated pages, `p_pos`.
+    pub fn page_cursor(&self) -> usize {
+        self.p_pos
+    }
+
+    /// Returns the avail-area `[b_pos, p_pos)` as `(start, size)`.
+    pub fn export_remaining(&self) -> (usize, usize) {
+        (self.b_pos, self.region_free())
+    }
+
+    /// Gives the avail-area to the formal allocator, see [`export_remaining`].
+    ///
+    /// Every later allocation fails. Live bytes and pages stay valid and can
+    /// still be freed, but their memory is never handed out again.
+    ///
+    /// [`export_remaining`]: EarlyAllocator::export_remaining
+    pub fn drain(&mut self) -> (usize, usize) {
+        let remaining = self.export_remaining();
+        self.p_pos = self.b_pos;
+        self.drained = true;
+        remaining
+    }
+
+    /// Returns whether [`EarlyAllocator::drain`] has been called.
+    pub fn is_drained(&self) -> bool {
+        self.drained
     }
 
+    /// Frees `num_pages` pages at `pos` if they are the last allocated block.
+    ///
+    /// Any other block is left allocated, and `false` is returned.
+    pub fn try_dealloc_pages(&mut self, pos: usize, num_pages: usize) -> bool {
+        let end = num_pages
+            .checked_mul(PAGE)
+            .and_then(|bytes| pos.checked_add(bytes));
+        match end {
+            Some(end) if pos == self.p_pos && end <= self.start + self.size => {
+                self.p_pos = end;
+                self.page_count = self.page_count.saturating_sub(1);
+                debug_assert!(self.invariants_hold());
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl<const PAGE: usize> Default for EarlyAllocator<PAGE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const PAGE: usize> core::fmt::Debug for EarlyAllocator<PAGE> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("EarlyAllocator")
+            .field("start", &format_args!("{:#x}", self.start))
+            .field("size", &format_args!("{:#x}", self.size))
+            .field("b_pos", &format_args!("{:#x}", self.b_pos))
+            .field("p_pos", &format_args!("{:#x}", self.p_pos))
+            .field("count", &self.count)
+            .field("page_count", &self.page_count)
+            .field("used_bytes", &self.used_bytes())
+            .field("available_bytes", &self.available_bytes())
+            .field("used_pages", &self.used_pages())
+            .finish()
+    }
+}
+
+impl<const PAGE: usize> BaseAllocator for EarlyAllocator<PAGE> {
+    /// Initializes with `[start, start + size)`, see [`EarlyAllocator::try_init`].
+    ///
+    /// An invalid arena panics in debug builds, and leaves the allocator
+    /// unchanged otherwise.
+    fn init(&mut self, start: usize, size: usize) {
+        let res = self.try_init(start, size);
+        debug_assert!(res.is_ok(), "invalid arena {:#x} + {:#x}", start, size);
+    }
+
+    /// Extends the arena upward with `[start, start + size)`.
+    ///
+    /// The region must begin right at the end of the arena, and no pages may
+    /// be allocated, as they sit at the end and would split the avail-area.
     fn add_memory(&mut self, start: usize, size: usize) -> allocator::AllocResult {
-        if start + size > self.start + self.size {
+        let end = self.start + self.size;
+        if start != end || self.p_pos != end {
             return Err(allocator::AllocError::NoMemory);
         }
-        self.p_pos = start + size;
+        let new_end = start
+            .checked_add(size)
+            .ok_or(allocator::AllocError::NoMemory)?;
+        self.size += size;
+        self.p_pos = new_end;
         Ok(())
     }
 }
 
-impl<const SIZE: usize> ByteAllocator for EarlyAllocator<SIZE> {
+impl<const PAGE: usize> ByteAllocator for EarlyAllocator<PAGE> {
     fn alloc(
         &mut self,
         layout: core::alloc::Layout,
     ) -> allocator::AllocResult<core::ptr::NonNull<u8>> {
+        if self.drained {
+            return Err(allocator::AllocError::NoMemory);
+        }
         let size = layout.size();
         let align = layout.align();
+        // The masking below only works for power-of-two alignments
+        if !align.is_power_of_two() {
+            return Err(allocator::AllocError::InvalidParam);
+        }
         
-        // Calculate aligned position
-        let aligned_pos = (self.b_pos + align - 1) & !(align - 1);
+        // Calculate aligned position, failing rather than wrapping around
+        let aligned_pos = self
+            .b_pos
+            .checked_add(align - 1)
+            .ok_or(allocator::AllocError::NoMemory)?
+            & !(align - 1);
+        let end = aligned_pos
+            .checked_add(size)
+            .ok_or(allocator::AllocError::NoMemory)?;
         
         // Check if we have enough space
-        if aligned_pos + size > self.p_pos {
+        self.check_free(aligned_pos, end)?;
+        // An arena at address 0 cannot hand out its first bytes
+        let Some(ptr) = core::ptr::NonNull::new(aligned_pos as *mut u8) else {
             return Err(allocator::AllocError::NoMemory);
-        }
+        };
         
-        // Update b_pos and count
-        self.b_pos = aligned_pos + size;
+        // Update b_pos, count and the peak usage
+        self.b_pos = end;
         self.count += 1;
+        self.peak_bytes = self.peak_bytes.max(end - self.start);
+        debug_assert!(self.invariants_hold());
         
         // Return the aligned pointer
-        Ok(core::ptr::NonNull::new(aligned_pos as *mut u8).unwrap())
+        Ok(ptr)
     }
 
-    fn dealloc(&mut self, _pos: core::ptr::NonNull<u8>, _layout: core::alloc::Layout) {
+    fn dealloc(&mut self, pos: core::ptr::NonNull<u8>, layout: core::alloc::Layout) {
         // Decrement count
         if self.count > 0 {
             self.count -= 1;
         }
         
-        // If count reaches zero, reset b_pos to start
-        if self.count == 0 {
+        // The most recent allocation can be given back right away
+        let pos = pos.as_ptr() as usize;
+        if !self.drained && pos.checked_add(layout.size()) == Some(self.b_pos) {
+            self.b_pos = pos;
+        }
+        
+        // If count reaches zero, reset b_pos to start, unless the rest of
+        // the memory belongs to the formal allocator now
+        if self.count == 0 && !self.drained {
             self.b_pos = self.start;
         }
     }
@@ -99,57 +379,120 @@ impl<const SIZE: usize> ByteAllocator for EarlyAllocator<SIZE> {
     }
 
     fn available_bytes(&self) -> usize {
-        self.p_pos - self.b_pos
+        self.region_free()
     }
 }
 
-impl<const SIZE: usize> PageAllocator for EarlyAllocator<SIZE> {
-    const PAGE_SIZE: usize = SIZE;
+impl<const PAGE: usize> PageAllocator for EarlyAllocator<PAGE> {
+    const PAGE_SIZE: usize = PAGE;
 
     fn alloc_pages(
         &mut self,
         num_pages: usize,
         align_pow2: usize,
     ) -> allocator::AllocResult<usize> {
-        let required_bytes = num_pages * SIZE;
+        if self.drained {
+            return Err(allocator::AllocError::NoMemory);
+        }
+        if !align_pow2.is_power_of_two() {
+            return Err(allocator::AllocError::InvalidParam);
+        }
+        let required_bytes = num_pages
+            .checked_mul(PAGE)
+            .ok_or(allocator::AllocError::NoMemory)?;
         
         // Check if we have enough space
-        if required_bytes > self.p_pos - self.b_pos {
+        if required_bytes > self.region_free() {
             return Err(allocator::AllocError::NoMemory);
         }
         
-        // Calculate aligned position (aligning backward from p_pos)
+        // Calculate aligned position (aligning backward from p_pos),
+        // to at least a page
+        let align = align_pow2.max(PAGE);
         let unaligned_pos = self.p_pos - required_bytes;
-        let aligned_pos = unaligned_pos & !(align_pow2 - 1);
+        let aligned_pos = unaligned_pos & !(align - 1);
         
         // Check if aligned position doesn't overlap with b_pos
-        if aligned_pos < self.b_pos {
-            return Err(allocator::AllocError::NoMemory);
-        }
+        self.check_free(aligned_pos, aligned_pos + required_bytes)?;
         
-        // Update p_pos
+        // Update p_pos and page_count
         self.p_pos = aligned_pos;
+        self.page_count += 1;
+        debug_assert!(self.invariants_hold());
         
         Ok(aligned_pos)
     }
 
     fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
-        let required_bytes = num_pages * SIZE;
-        if pos + required_bytes > self.p_pos {
-            return;
-        }
-        self.p_pos = pos + required_bytes;
+        self.try_dealloc_pages(pos, num_pages);
     }
 
     fn total_pages(&self) -> usize {
-        self.size / SIZE
+        self.size / PAGE
     }
 
     fn used_pages(&self) -> usize {
-        (self.p_pos - self.start) / SIZE
+        (self.start + self.size - self.p_pos) / PAGE
     }
 
     fn available_pages(&self) -> usize {
-        (self.p_pos - self.b_pos) / SIZE
+        self.region_free() / PAGE
     }
-}
\ No newline at end of file
+}
+
+/// An [`EarlyAllocator`] behind a lock, usable as a `#[global_allocator]`.
+///
+/// Failed allocations return a null pointer, as [`GlobalAlloc`] requires.
+pub struct LockedEarlyAllocator<const PAGE: usize> {
+    inner: SpinNoIrq<EarlyAllocator<PAGE>>,
+}
+
+impl<const PAGE: usize> LockedEarlyAllocator<PAGE> {
+    /// Creates an allocator that fails every allocation until `init`.
+    pub const fn new() -> Self {
+        Self {
+            inner: SpinNoIrq::new(EarlyAllocator::new()),
+        }
+    }
+
+    /// Initializes the allocator with the given region.
+    pub fn init(&self, start: usize, size: usize) {
+        self.inner.lock().init(start, size);
+    }
+
+    /// Returns the number of live byte allocations.
+    pub fn alloc_count(&self) -> usize {
+        self.inner.lock().alloc_count()
+    }
+
+    /// Returns the number of bytes used by the bytes area.
+    pub fn used_bytes(&self) -> usize {
+        self.inner.lock().used_bytes()
+    }
+
+    /// Hands the avail-area over, see [`EarlyAllocator::drain`].
+    pub fn drain(&self) -> (usize, usize) {
+        self.inner.lock().drain()
+    }
+}
+
+impl<const PAGE: usize> Default for LockedEarlyAllocator<PAGE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const PAGE: usize> GlobalAlloc for LockedEarlyAllocator<PAGE> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.inner.lock().alloc(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(_) => core::ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(ptr) = core::ptr::NonNull::new(ptr) {
+            self.inner.lock().dealloc(ptr, layout);
+        }
+    }
+}
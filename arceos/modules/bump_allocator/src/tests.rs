@@ -0,0 +1,647 @@
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+use allocator::{AllocError, BaseAllocator, ByteAllocator, PageAllocator};
+
+use crate::{EarlyAllocator, LockedEarlyAllocator};
+
+const PAGE_SIZE: usize = 0x1000;
+/// The allocator never touches its memory, so a made-up arena is enough.
+const ARENA_START: usize = 0x8000_0000;
+const ARENA_SIZE: usize = 64 * PAGE_SIZE;
+
+const NUM_SEEDS: u64 = 64;
+const NUM_OPS: usize = 2000;
+
+/// xorshift64, so that failures can be replayed from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Region {
+    Bytes(usize, Layout),
+    Pages(usize, usize),
+}
+
+impl Region {
+    fn range(&self) -> core::ops::Range<usize> {
+        match *self {
+            Region::Bytes(pos, layout) => pos..pos + layout.size(),
+            Region::Pages(pos, num) => pos..pos + num * PAGE_SIZE,
+        }
+    }
+}
+
+fn check_invariants(alloc: &EarlyAllocator<PAGE_SIZE>, live: &[Region], seed: u64) {
+    let end = alloc.start + alloc.size;
+    assert!(alloc.start <= alloc.b_pos, "seed {seed}: b_pos below start");
+    assert!(alloc.b_pos <= alloc.p_pos, "seed {seed}: b_pos passed p_pos");
+    assert!(alloc.p_pos <= end, "seed {seed}: p_pos beyond end");
+
+    let bytes = live.iter().filter(|r| matches!(r, Region::Bytes(..))).count();
+    assert_eq!(alloc.alloc_count(), bytes, "seed {seed}: count out of balance");
+    if bytes == 0 {
+        assert_eq!(alloc.b_pos, alloc.start, "seed {seed}: bytes area not reset");
+    }
+
+    for region in live {
+        let range = region.range();
+        match region {
+            Region::Bytes(..) => assert!(
+                alloc.start <= range.start && range.end <= alloc.b_pos,
+                "seed {seed}: {region:?} outside the bytes area"
+            ),
+            Region::Pages(..) => assert!(
+                alloc.p_pos <= range.start && range.end <= end,
+                "seed {seed}: {region:?} outside the pages area"
+            ),
+        }
+    }
+}
+
+fn check_no_overlap(new: Region, live: &[Region], seed: u64) {
+    let range = new.range();
+    for other in live {
+        let other_range = other.range();
+        assert!(
+            range.is_empty() || range.end <= other_range.start || other_range.end <= range.start,
+            "seed {seed}: {new:?} overlaps {other:?}"
+        );
+    }
+}
+
+fn run_sequence(seed: u64) {
+    let mut rng = Rng(seed);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(ARENA_START, ARENA_SIZE);
+    let mut live = Vec::new();
+
+    for _ in 0..NUM_OPS {
+        match rng.below(4) {
+            0 => {
+                let size = 1 + rng.below(512);
+                let align = 1 << rng.below(7);
+                let layout = Layout::from_size_align(size, align).unwrap();
+                if let Ok(ptr) = alloc.alloc(layout) {
+                    let pos = ptr.as_ptr() as usize;
+                    assert_eq!(pos % align, 0, "seed {seed}: misaligned bytes");
+                    let region = Region::Bytes(pos, layout);
+                    check_no_overlap(region, &live, seed);
+                    live.push(region);
+                }
+            }
+            2 => {
+                let num_pages = 1 + rng.below(4);
+                let align = PAGE_SIZE << rng.below(3);
+                if let Ok(pos) = alloc.alloc_pages(num_pages, align) {
+                    assert_eq!(pos % align, 0, "seed {seed}: misaligned pages");
+                    let region = Region::Pages(pos, num_pages);
+                    check_no_overlap(region, &live, seed);
+                    live.push(region);
+                }
+            }
+            op if !live.is_empty() => {
+                let want_bytes = op == 1;
+                let candidates: Vec<_> = (0..live.len())
+                    .filter(|&i| matches!(live[i], Region::Bytes(..)) == want_bytes)
+                    .collect();
+                if candidates.is_empty() {
+                    continue;
+                }
+                match live.swap_remove(candidates[rng.below(candidates.len())]) {
+                    Region::Bytes(pos, layout) => {
+                        alloc.dealloc(NonNull::new(pos as *mut u8).unwrap(), layout)
+                    }
+                    Region::Pages(pos, num_pages) => alloc.dealloc_pages(pos, num_pages),
+                }
+            }
+            _ => {}
+        }
+        check_invariants(&alloc, &live, seed);
+    }
+}
+
+#[test]
+fn test_random_sequences() {
+    for seed in 1..=NUM_SEEDS {
+        run_sequence(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    }
+}
+
+#[test]
+fn test_handoff() {
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(ARENA_START, ARENA_SIZE);
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    let bytes = alloc.alloc(layout).unwrap();
+    alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+
+    let available = alloc.available_bytes();
+    let (start, size) = alloc.export_remaining();
+    assert_eq!(size, available);
+    assert_eq!(start, alloc.b_pos);
+    assert_eq!(start + size, alloc.p_pos);
+
+    assert_eq!(alloc.drain(), (start, size));
+    assert!(alloc.is_drained());
+    assert_eq!(alloc.available_bytes(), 0);
+    assert!(alloc.alloc(layout).is_err());
+    assert!(alloc.alloc_pages(1, PAGE_SIZE).is_err());
+
+    // Freeing the last bytes must not reclaim memory the formal allocator owns.
+    alloc.dealloc(bytes, layout);
+    assert_eq!(alloc.alloc_count(), 0);
+    assert_eq!(alloc.export_remaining().1, 0);
+    assert!(alloc.alloc(layout).is_err());
+}
+
+fn page_allocator() -> EarlyAllocator<PAGE_SIZE> {
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(ARENA_START, ARENA_SIZE);
+    alloc
+}
+
+#[test]
+fn test_dealloc_pages_lifo() {
+    let mut alloc = page_allocator();
+    let a = alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+    let b = alloc.alloc_pages(3, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.p_pos, b);
+
+    assert!(alloc.try_dealloc_pages(b, 3));
+    assert_eq!(alloc.p_pos, a);
+    assert!(alloc.try_dealloc_pages(a, 2));
+    assert_eq!(alloc.p_pos, ARENA_START + ARENA_SIZE);
+    assert_eq!(alloc.available_pages(), ARENA_SIZE / PAGE_SIZE);
+}
+
+#[test]
+fn test_dealloc_pages_out_of_order() {
+    let mut alloc = page_allocator();
+    let a = alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+    let b = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+
+    // `a` is below the top block `b`, so freeing it is ignored.
+    assert!(!alloc.try_dealloc_pages(a, 2));
+    assert_eq!(alloc.p_pos, b);
+    // The trait method ignores it the same way.
+    alloc.dealloc_pages(a, 2);
+    assert_eq!(alloc.p_pos, b);
+
+    assert!(alloc.try_dealloc_pages(b, 1));
+    assert!(alloc.try_dealloc_pages(a, 2));
+    assert_eq!(alloc.p_pos, ARENA_START + ARENA_SIZE);
+}
+
+#[test]
+fn test_dealloc_pages_never_allocated() {
+    let mut alloc = page_allocator();
+    let top = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+
+    // Below the pages area, in the avail-area.
+    assert!(!alloc.try_dealloc_pages(top - 4 * PAGE_SIZE, 1));
+    // Right at the top block, but larger than the arena.
+    assert!(!alloc.try_dealloc_pages(top, 2));
+    assert!(!alloc.try_dealloc_pages(top, usize::MAX));
+    assert_eq!(alloc.p_pos, top);
+
+    let mut empty = page_allocator();
+    assert!(!empty.try_dealloc_pages(ARENA_START + ARENA_SIZE, 1));
+    assert_eq!(empty.p_pos, ARENA_START + ARENA_SIZE);
+}
+
+/// An allocator whose arena ends at the very top of the address space.
+fn top_allocator() -> EarlyAllocator<PAGE_SIZE> {
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(usize::MAX - ARENA_SIZE, ARENA_SIZE);
+    alloc
+}
+
+#[test]
+fn test_alloc_oversized() {
+    let mut alloc = top_allocator();
+    let huge = Layout::from_size_align(isize::MAX as usize, 1).unwrap();
+    assert_eq!(alloc.alloc(huge), Err(AllocError::NoMemory));
+
+    // Aligning up from the arena would wrap past the end of the address space.
+    let aligned = Layout::from_size_align(1, 1 << (usize::BITS - 2)).unwrap();
+    assert_eq!(alloc.alloc(aligned), Err(AllocError::NoMemory));
+    assert_eq!(alloc.b_pos, alloc.start);
+    assert_eq!(alloc.alloc_count(), 0);
+
+    // With `b_pos` at the top of the arena, even one byte overflows.
+    let whole = Layout::from_size_align(ARENA_SIZE, 1).unwrap();
+    alloc.alloc(whole).unwrap();
+    assert_eq!(alloc.b_pos, usize::MAX);
+    let one = Layout::from_size_align(1, 1).unwrap();
+    assert_eq!(alloc.alloc(one), Err(AllocError::NoMemory));
+    assert_eq!(alloc.alloc_count(), 1);
+}
+
+#[test]
+fn test_alloc_full_arena() {
+    let mut alloc = top_allocator();
+    let whole = Layout::from_size_align(ARENA_SIZE, 1).unwrap();
+    alloc.alloc(whole).unwrap();
+    assert_eq!(alloc.b_pos, alloc.p_pos);
+
+    let one = Layout::from_size_align(1, 1).unwrap();
+    assert_eq!(alloc.alloc(one), Err(AllocError::NoMemory));
+    let aligned = Layout::from_size_align(1, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.alloc(aligned), Err(AllocError::NoMemory));
+    assert_eq!(alloc.alloc_count(), 1);
+}
+
+#[test]
+fn test_alloc_pages_bad_align() {
+    let mut alloc = page_allocator();
+    assert_eq!(alloc.alloc_pages(1, 3), Err(AllocError::InvalidParam));
+    assert_eq!(alloc.alloc_pages(1, 0), Err(AllocError::InvalidParam));
+    assert_eq!(alloc.alloc_pages(1, PAGE_SIZE + 1), Err(AllocError::InvalidParam));
+    assert_eq!(alloc.p_pos, ARENA_START + ARENA_SIZE);
+    assert!(alloc.alloc_pages(1, PAGE_SIZE).is_ok());
+}
+
+#[test]
+fn test_reset() {
+    let mut alloc = page_allocator();
+    alloc.alloc(Layout::from_size_align(100, 8).unwrap()).unwrap();
+    alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+    alloc.drain();
+
+    alloc.reset();
+    assert_eq!(alloc.used_bytes(), 0);
+    assert_eq!(alloc.available_bytes(), alloc.total_bytes());
+    assert_eq!(alloc.alloc_count(), 0);
+    assert!(!alloc.is_drained());
+    assert!(alloc.alloc_pages(1, PAGE_SIZE).is_ok());
+
+    let mut uninit = EarlyAllocator::<PAGE_SIZE>::new();
+    uninit.reset();
+    assert_eq!(uninit.available_bytes(), 0);
+    assert!(uninit.alloc(Layout::from_size_align(1, 1).unwrap()).is_err());
+}
+
+#[test]
+fn test_cursors() {
+    let mut alloc = page_allocator();
+    assert_eq!(alloc.byte_cursor(), ARENA_START);
+    assert_eq!(alloc.page_cursor(), ARENA_START + ARENA_SIZE);
+
+    alloc.alloc(Layout::from_size_align(24, 8).unwrap()).unwrap();
+    assert_eq!(alloc.byte_cursor(), ARENA_START + 24);
+    let pos = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.page_cursor(), pos);
+    assert_eq!(pos, ARENA_START + ARENA_SIZE - PAGE_SIZE);
+    assert_eq!(alloc.byte_cursor(), ARENA_START + 24);
+}
+
+#[test]
+fn test_debug() {
+    let mut alloc = page_allocator();
+    alloc.alloc(Layout::from_size_align(24, 8).unwrap()).unwrap();
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+
+    let out = format!("{:?}", alloc);
+    assert!(out.starts_with("EarlyAllocator {"), "{out}");
+    assert!(out.contains(&format!("b_pos: {:#x}", alloc.byte_cursor())), "{out}");
+    assert!(out.contains(&format!("p_pos: {:#x}", alloc.page_cursor())), "{out}");
+    assert!(out.contains("count: 1"), "{out}");
+    assert!(out.contains("used_bytes: 24"), "{out}");
+}
+
+#[test]
+fn test_dealloc_bytes_lifo() {
+    let mut alloc = page_allocator();
+    let layout = Layout::from_size_align(40, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap();
+    let b = alloc.alloc(layout).unwrap();
+    let c = alloc.alloc(layout).unwrap();
+
+    alloc.dealloc(c, layout);
+    assert_eq!(alloc.byte_cursor(), c.as_ptr() as usize);
+    alloc.dealloc(b, layout);
+    assert_eq!(alloc.byte_cursor(), b.as_ptr() as usize);
+    assert_eq!(alloc.alloc_count(), 1);
+
+    // The space of `b` is reused while `a` is still live.
+    assert_eq!(alloc.alloc(layout).unwrap(), b);
+    assert_eq!(alloc.alloc_count(), 2);
+    assert_eq!(a.as_ptr() as usize, ARENA_START);
+}
+
+#[test]
+fn test_dealloc_bytes_out_of_order() {
+    let mut alloc = page_allocator();
+    let layout = Layout::from_size_align(40, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap();
+    let b = alloc.alloc(layout).unwrap();
+    let top = alloc.byte_cursor();
+
+    // `a` is not the most recent allocation, only the count goes down.
+    alloc.dealloc(a, layout);
+    assert_eq!(alloc.byte_cursor(), top);
+    assert_eq!(alloc.alloc_count(), 1);
+
+    // The last free empties the bytes area.
+    alloc.dealloc(b, layout);
+    assert_eq!(alloc.byte_cursor(), ARENA_START);
+    assert_eq!(alloc.alloc_count(), 0);
+}
+
+#[test]
+fn test_alloc_zeroed() {
+    let mut arena = vec![0xa5u8; 4 * PAGE_SIZE];
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(arena.as_mut_ptr() as usize, arena.len());
+
+    let layout = Layout::from_size_align(1000, 16).unwrap();
+    let ptr = alloc.alloc_zeroed(layout).unwrap();
+    let buf = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), layout.size()) };
+    assert!(buf.iter().all(|&b| b == 0));
+    // Nothing past the allocation is touched.
+    let end = ptr.as_ptr() as usize - arena.as_ptr() as usize + layout.size();
+    assert_eq!(arena[end], 0xa5);
+
+    let too_big = Layout::from_size_align(arena.len() + 1, 1).unwrap();
+    assert_eq!(alloc.alloc_zeroed(too_big), Err(AllocError::NoMemory));
+}
+
+#[test]
+fn test_add_memory_contiguous() {
+    let mut alloc = page_allocator();
+    alloc.alloc(Layout::from_size_align(100, 8).unwrap()).unwrap();
+    let end = ARENA_START + ARENA_SIZE;
+
+    assert_eq!(alloc.add_memory(end, 4 * PAGE_SIZE), Ok(()));
+    assert_eq!(alloc.total_bytes(), ARENA_SIZE + 4 * PAGE_SIZE);
+    assert_eq!(alloc.page_cursor(), end + 4 * PAGE_SIZE);
+    assert_eq!(alloc.available_bytes(), ARENA_SIZE + 4 * PAGE_SIZE - 100);
+    assert_eq!(alloc.alloc_pages(1, PAGE_SIZE), Ok(end + 3 * PAGE_SIZE));
+}
+
+#[test]
+fn test_add_memory_rejected() {
+    let mut alloc = page_allocator();
+    let end = ARENA_START + ARENA_SIZE;
+
+    // Disjoint, below the arena, and overlapping its end.
+    assert_eq!(alloc.add_memory(end + PAGE_SIZE, PAGE_SIZE), Err(AllocError::NoMemory));
+    assert_eq!(alloc.add_memory(ARENA_START - PAGE_SIZE, PAGE_SIZE), Err(AllocError::NoMemory));
+    assert_eq!(alloc.add_memory(end - PAGE_SIZE, 2 * PAGE_SIZE), Err(AllocError::NoMemory));
+    assert_eq!(alloc.add_memory(end, usize::MAX), Err(AllocError::NoMemory));
+
+    // Allocated pages sit at the end, so the arena cannot grow past them.
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.add_memory(end, PAGE_SIZE), Err(AllocError::NoMemory));
+    assert_eq!(alloc.total_bytes(), ARENA_SIZE);
+    assert_eq!(alloc.page_cursor(), end - PAGE_SIZE);
+}
+
+#[test]
+fn test_peak_used_bytes() {
+    let mut alloc = page_allocator();
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    let ptrs: Vec<_> = (0..10).map(|_| alloc.alloc(layout).unwrap()).collect();
+    assert_eq!(alloc.peak_used_bytes(), 640);
+
+    for ptr in ptrs {
+        alloc.dealloc(ptr, layout);
+    }
+    assert_eq!(alloc.used_bytes(), 0);
+    assert_eq!(alloc.peak_used_bytes(), 640);
+
+    // A smaller burst afterwards keeps the old peak.
+    let ptr = alloc.alloc(layout).unwrap();
+    alloc.dealloc(ptr, layout);
+    assert_eq!(alloc.peak_used_bytes(), 640);
+
+    alloc.reset();
+    assert_eq!(alloc.peak_used_bytes(), 0);
+}
+
+#[test]
+fn test_alloc_at_null() {
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(0, ARENA_SIZE);
+    let layout = Layout::from_size_align(16, 8).unwrap();
+    assert_eq!(alloc.alloc(layout), Err(AllocError::NoMemory));
+    assert_eq!(alloc.alloc_count(), 0);
+    assert_eq!(alloc.byte_cursor(), 0);
+
+    // Pages come from the end of the arena, away from address 0.
+    assert_eq!(alloc.alloc_pages(1, PAGE_SIZE), Ok(ARENA_SIZE - PAGE_SIZE));
+}
+
+#[test]
+fn test_cursors_meet() {
+    let mut alloc = page_allocator();
+    let layout = Layout::from_size_align(PAGE_SIZE / 2, 8).unwrap();
+    // Take from both ends in turn until neither side fits any more.
+    loop {
+        let bytes = alloc.alloc(layout).is_ok();
+        let pages = alloc.alloc_pages(1, PAGE_SIZE).is_ok();
+        if !bytes && !pages {
+            break;
+        }
+        assert!(alloc.byte_cursor() <= alloc.page_cursor());
+    }
+    assert_eq!(alloc.byte_cursor(), alloc.page_cursor());
+    assert_eq!(alloc.available_bytes(), 0);
+    assert_eq!(alloc.alloc(Layout::from_size_align(1, 1).unwrap()), Err(AllocError::NoMemory));
+    assert_eq!(alloc.alloc_pages(1, PAGE_SIZE), Err(AllocError::NoMemory));
+    assert_eq!(alloc.byte_cursor(), alloc.page_cursor());
+}
+
+#[test]
+fn test_page_counts() {
+    const MIB: usize = 1 << 20;
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(ARENA_START, MIB);
+    assert_eq!(alloc.total_pages(), 256);
+    assert_eq!(alloc.used_pages(), 0);
+    assert_eq!(alloc.available_pages(), 256);
+
+    alloc.alloc_pages(3, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.used_pages(), 3);
+    assert_eq!(alloc.available_pages(), 253);
+
+    // The bytes area takes from the avail-area, not from the used pages.
+    alloc.alloc(Layout::from_size_align(PAGE_SIZE + 1, 1).unwrap()).unwrap();
+    assert_eq!(alloc.used_pages(), 3);
+    assert_eq!(alloc.available_pages(), 251);
+    assert_eq!(alloc.total_pages(), 256);
+}
+
+#[test]
+fn test_try_init() {
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    assert_eq!(alloc.try_init(usize::MAX, PAGE_SIZE), Err(AllocError::InvalidParam));
+    assert_eq!(alloc.try_init(ARENA_START, 0), Err(AllocError::InvalidParam));
+    assert_eq!(alloc.total_bytes(), 0);
+    assert!(alloc.alloc(Layout::from_size_align(1, 1).unwrap()).is_err());
+
+    assert_eq!(alloc.try_init(usize::MAX - PAGE_SIZE, PAGE_SIZE), Ok(()));
+    assert_eq!(alloc.total_bytes(), PAGE_SIZE);
+    assert_eq!(alloc.page_cursor(), usize::MAX);
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "invalid arena")]
+fn test_init_wrapping() {
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(usize::MAX, PAGE_SIZE);
+}
+
+#[test]
+fn test_locked_global_alloc() {
+    use core::alloc::GlobalAlloc;
+
+    let mut arena = vec![0u8; 4 * PAGE_SIZE];
+    let locked = LockedEarlyAllocator::<PAGE_SIZE>::new();
+    locked.init(arena.as_mut_ptr() as usize, arena.len());
+
+    let layout = Layout::array::<u64>(16).unwrap();
+    let too_big = Layout::from_size_align(arena.len() + 1, 1).unwrap();
+    unsafe {
+        let ptr = locked.alloc(layout) as *mut u64;
+        assert!(!ptr.is_null());
+        assert_eq!(ptr as usize % layout.align(), 0);
+        for i in 0..16 {
+            ptr.add(i).write(i as u64);
+        }
+        assert_eq!(ptr.add(15).read(), 15);
+
+        // A failed allocation is reported as a null pointer.
+        assert!(locked.alloc(too_big).is_null());
+        assert_eq!(locked.alloc_count(), 1);
+
+        locked.dealloc(ptr as *mut u8, layout);
+    }
+    assert_eq!(locked.alloc_count(), 0);
+    assert_eq!(locked.used_bytes(), 0);
+}
+
+#[test]
+fn test_invariants_near_full() {
+    let mut alloc = page_allocator();
+    assert!(alloc.invariants_hold());
+
+    // Leave a single page between the two areas, then fill it odd-sized.
+    alloc.alloc_pages(ARENA_SIZE / PAGE_SIZE / 2, PAGE_SIZE).unwrap();
+    let bytes = ARENA_SIZE / 2 - PAGE_SIZE;
+    alloc.alloc(Layout::from_size_align(bytes, 1).unwrap()).unwrap();
+    assert!(alloc.invariants_hold());
+    assert_eq!(alloc.available_pages(), 1);
+
+    alloc.alloc(Layout::from_size_align(PAGE_SIZE - 1, 1).unwrap()).unwrap();
+    assert!(alloc.invariants_hold());
+    assert_eq!(alloc.available_bytes(), 1);
+    assert_eq!(alloc.available_pages(), 0);
+    assert!(alloc.alloc_pages(1, PAGE_SIZE).is_err());
+    assert!(alloc.invariants_hold());
+
+    alloc.alloc(Layout::from_size_align(1, 1).unwrap()).unwrap();
+    assert!(alloc.invariants_hold());
+    assert_eq!(alloc.available_bytes(), 0);
+    assert!(alloc.alloc(Layout::from_size_align(1, 1).unwrap()).is_err());
+    assert!(alloc.invariants_hold());
+}
+
+#[test]
+fn test_shrink() {
+    let mut alloc = page_allocator();
+    let old = Layout::from_size_align(256, 8).unwrap();
+    let new = Layout::from_size_align(64, 8).unwrap();
+    let a = alloc.alloc(old).unwrap();
+    let b = alloc.alloc(old).unwrap();
+
+    // `b` is the most recent allocation, so its tail is freed.
+    assert_eq!(alloc.shrink(b, old, new), Ok(b));
+    assert_eq!(alloc.used_bytes(), 256 + 64);
+
+    // `a` sits below `b` and keeps its space.
+    assert_eq!(alloc.shrink(a, old, new), Ok(a));
+    assert_eq!(alloc.used_bytes(), 256 + 64);
+
+    assert_eq!(alloc.shrink(b, new, old), Err(AllocError::InvalidParam));
+    assert_eq!(alloc.alloc_count(), 2);
+}
+
+#[test]
+fn test_grow() {
+    let mut alloc = page_allocator();
+    let old = Layout::from_size_align(64, 8).unwrap();
+    let new = Layout::from_size_align(256, 8).unwrap();
+    let a = alloc.alloc(old).unwrap();
+    let b = alloc.alloc(old).unwrap();
+
+    assert_eq!(alloc.grow(a, old, new), Err(AllocError::NoMemory));
+    assert_eq!(alloc.grow(b, old, new), Ok(b));
+    assert_eq!(alloc.used_bytes(), 64 + 256);
+    assert_eq!(alloc.peak_used_bytes(), 64 + 256);
+}
+
+#[test]
+fn test_grow_blocked_by_pages() {
+    let mut alloc = page_allocator();
+    let old = Layout::from_size_align(64, 8).unwrap();
+    let ptr = alloc.alloc(old).unwrap();
+    alloc.alloc_pages(ARENA_SIZE / PAGE_SIZE - 1, PAGE_SIZE).unwrap();
+
+    let fits = Layout::from_size_align(PAGE_SIZE, 8).unwrap();
+    let too_big = Layout::from_size_align(PAGE_SIZE + 1, 8).unwrap();
+    assert_eq!(alloc.grow(ptr, old, too_big), Err(AllocError::NoMemory));
+    assert_eq!(alloc.used_bytes(), 64);
+    assert_eq!(alloc.grow(ptr, old, fits), Ok(ptr));
+    assert_eq!(alloc.available_bytes(), 0);
+}
+
+#[test]
+fn test_alloc_counts() {
+    let mut alloc = page_allocator();
+    let layout = Layout::from_size_align(32, 8).unwrap();
+    let bytes: Vec<_> = (0..5).map(|_| alloc.alloc(layout).unwrap()).collect();
+    let pages: Vec<_> = (1..=3).map(|n| (alloc.alloc_pages(n, PAGE_SIZE).unwrap(), n)).collect();
+    assert_eq!(alloc.byte_alloc_count(), 5);
+    assert_eq!(alloc.page_alloc_count(), 3);
+
+    // Page frees out of order are ignored, and so not counted.
+    alloc.dealloc_pages(pages[0].0, pages[0].1);
+    assert_eq!(alloc.page_alloc_count(), 3);
+
+    for &ptr in bytes.iter().rev() {
+        alloc.dealloc(ptr, layout);
+    }
+    for &(pos, n) in pages.iter().rev() {
+        alloc.dealloc_pages(pos, n);
+    }
+    assert_eq!(alloc.byte_alloc_count(), 0);
+    assert_eq!(alloc.page_alloc_count(), 0);
+    assert_eq!(alloc.available_pages(), ARENA_SIZE / PAGE_SIZE);
+}
+
+#[test]
+fn test_alloc_pages_small_align() {
+    // The arena ends off a page boundary, so `p_pos` starts unaligned.
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(ARENA_START, ARENA_SIZE - 0x10);
+
+    let pos = alloc.alloc_pages(1, 1).unwrap();
+    assert_eq!(pos % PAGE_SIZE, 0);
+    assert_eq!(pos, ARENA_START + ARENA_SIZE - 2 * PAGE_SIZE);
+    assert!(alloc.invariants_hold());
+}
@@ -0,0 +1,96 @@
+//! Runs `Box`, `Vec` and `HashMap` on top of [`LockedEarlyAllocator`] as the
+//! global allocator.
+//!
+//! The std runtime allocates before `main`, so allocations go to the system
+//! allocator until the arena is armed, and each free is routed by address.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use bump_allocator::LockedEarlyAllocator;
+
+const PAGE_SIZE: usize = 0x1000;
+const ARENA_SIZE: usize = 1 << 20;
+
+#[repr(C, align(4096))]
+struct Arena([u8; ARENA_SIZE]);
+
+static mut ARENA: Arena = Arena([0; ARENA_SIZE]);
+static ARMED: AtomicBool = AtomicBool::new(false);
+static EARLY: LockedEarlyAllocator<PAGE_SIZE> = LockedEarlyAllocator::new();
+
+fn arena_start() -> usize {
+    core::ptr::addr_of!(ARENA) as usize
+}
+
+fn in_arena(ptr: *const u8) -> bool {
+    (arena_start()..arena_start() + ARENA_SIZE).contains(&(ptr as usize))
+}
+
+struct Router;
+
+unsafe impl GlobalAlloc for Router {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if ARMED.load(Ordering::Relaxed) {
+            EARLY.alloc(layout)
+        } else {
+            System.alloc(layout)
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if in_arena(ptr) {
+            EARLY.dealloc(ptr, layout)
+        } else {
+            System.dealloc(ptr, layout)
+        }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Router = Router;
+
+/// The `axstd` `HashMap` needs the ArceOS runtime, so this uses the same
+/// `hashbrown` map and hasher that it wraps.
+type HashMap<K, V> = hashbrown::HashMap<K, V, hashbrown::DefaultHashBuilder>;
+
+fn exercise_collections() {
+    let boxed = Box::new(0xdead_beef_u64);
+    assert!(in_arena(&*boxed as *const u64 as *const u8));
+    assert_eq!(*boxed, 0xdead_beef);
+
+    let mut vec = Vec::new();
+    for i in 0..1000u32 {
+        vec.push(i);
+    }
+    assert!(in_arena(vec.as_ptr() as *const u8));
+    assert_eq!(vec.iter().sum::<u32>(), 999 * 1000 / 2);
+
+    let mut map = HashMap::default();
+    for i in 0..1000u32 {
+        map.insert(i, i * 2);
+    }
+    assert_eq!(map.len(), 1000);
+    assert!((0..1000u32).all(|i| map.get(&i) == Some(&(i * 2))));
+    map.retain(|k, _| k % 2 == 0);
+    assert_eq!(map.len(), 500);
+
+    let mut tree = BTreeMap::new();
+    for (k, v) in &map {
+        tree.insert(*k, *v);
+    }
+    assert_eq!(tree.len(), 500);
+    assert!(EARLY.alloc_count() > 0);
+}
+
+fn main() {
+    EARLY.init(arena_start(), ARENA_SIZE);
+    ARMED.store(true, Ordering::SeqCst);
+    exercise_collections();
+    ARMED.store(false, Ordering::SeqCst);
+
+    assert_eq!(EARLY.alloc_count(), 0);
+    assert_eq!(EARLY.used_bytes(), 0);
+    println!("global_alloc ok");
+}
@@ -1,9 +1,30 @@
+#define _GNU_SOURCE
 #include <stdio.h>
 #include <stdlib.h>
 #include <string.h>
 #include <unistd.h>
 #include <fcntl.h>
+#include <errno.h>
+#include <sched.h>
+#include <signal.h>
+#include <dirent.h>
+#include <time.h>
+#include <poll.h>
+#include <linux/futex.h>
+#include <sys/ioctl.h>
 #include <sys/mman.h>
+#include <sys/random.h>
+#include <sys/resource.h>
+#include <sys/select.h>
+#include <sys/sendfile.h>
+#include <sys/stat.h>
+#include <sys/statfs.h>
+#include <sys/syscall.h>
+#include <sys/sysinfo.h>
+#include <sys/times.h>
+#include <sys/utsname.h>
+#include <sys/wait.h>
+#include <sys/uio.h>
 
 void create_file(const char *fname)
 {
@@ -44,6 +65,2150 @@ void verify_file(const char *fname)
     close(fd);
 }
 
+void test_mremap(void)
+{
+    char *addr, *grown, *blocker, *moved;
+
+    addr = mmap(NULL, 4096, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    addr[0] = 'a';
+
+    /* The pages right after a fresh mapping are free, so it grows in place. */
+    grown = mremap(addr, 4096, 8192, 0);
+    if (grown != addr) {
+        printf("Mremap in place error!\n");
+        exit(-1);
+    }
+    grown[4096] = 'b';
+
+    /* Occupy the next page so that growing again has to move the mapping. */
+    blocker = mmap(addr + 8192, 4096, PROT_READ|PROT_WRITE,
+                   MAP_PRIVATE|MAP_ANONYMOUS|MAP_FIXED, -1, 0);
+    if (blocker != addr + 8192) {
+        printf("Map blocker error!\n");
+        exit(-1);
+    }
+    moved = mremap(grown, 8192, 16384, MREMAP_MAYMOVE);
+    if (moved == MAP_FAILED || moved == grown) {
+        printf("Mremap move error!\n");
+        exit(-1);
+    }
+    if (moved[0] != 'a' || moved[4096] != 'b') {
+        printf("Mremap content error!\n");
+        exit(-1);
+    }
+    printf("Mremap ok!\n");
+}
+
+void test_brk(void)
+{
+    char *cur, *grown;
+
+    /* musl's brk()/sbrk() refuse to move the break, so call it directly. */
+    cur = (char *)syscall(SYS_brk, 0);
+    if (cur != (char *)syscall(SYS_brk, 0)) {
+        printf("Brk query error!\n");
+        exit(-1);
+    }
+    grown = (char *)syscall(SYS_brk, cur + 4096);
+    if (grown != cur + 4096) {
+        printf("Brk grow error!\n");
+        exit(-1);
+    }
+    cur[0] = 'h';
+    cur[4095] = 'p';
+    if (cur[0] != 'h' || cur[4095] != 'p') {
+        printf("Brk content error!\n");
+        exit(-1);
+    }
+    printf("Brk ok!\n");
+}
+
+void test_madvise(void)
+{
+    char *addr;
+    int i;
+
+    addr = mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    memset(addr, 0x5a, 8192);
+    if (madvise(addr, 8192, MADV_DONTNEED) != 0) {
+        printf("Madvise error!\n");
+        exit(-1);
+    }
+    for (i = 0; i < 8192; i++) {
+        if (addr[i] != 0) {
+            printf("Madvise content error!\n");
+            exit(-1);
+        }
+    }
+    if (madvise(addr, (size_t)-1, MADV_DONTNEED) == 0 || errno != EINVAL) {
+        printf("Madvise accepted a wrapping length!\n");
+        exit(-1);
+    }
+    printf("Madvise ok!\n");
+}
+
+void test_getpid(void)
+{
+    pid_t pid = getpid();
+
+    if (pid <= 0 || getpid() != pid) {
+        printf("Getpid error!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_gettid) <= 0) {
+        printf("Gettid error!\n");
+        exit(-1);
+    }
+    printf("Getpid ok!\n");
+}
+
+void test_clock_gettime(void)
+{
+    struct timespec t0, t1;
+
+    if (clock_gettime(CLOCK_MONOTONIC, &t0) != 0 ||
+        clock_gettime(CLOCK_MONOTONIC, &t1) != 0) {
+        printf("Clock_gettime error!\n");
+        exit(-1);
+    }
+    if (t1.tv_sec < t0.tv_sec ||
+        (t1.tv_sec == t0.tv_sec && t1.tv_nsec < t0.tv_nsec)) {
+        printf("Clock_gettime monotonic error!\n");
+        exit(-1);
+    }
+    printf("Clock_gettime ok!\n");
+}
+
+void test_nanosleep(void)
+{
+    struct timespec req = { 0, 10 * 1000 * 1000 };
+    struct timespec t0, t1;
+    long elapsed;
+
+    clock_gettime(CLOCK_MONOTONIC, &t0);
+    if (nanosleep(&req, NULL) != 0) {
+        printf("Nanosleep error!\n");
+        exit(-1);
+    }
+    clock_gettime(CLOCK_MONOTONIC, &t1);
+    elapsed = (t1.tv_sec - t0.tv_sec) * 1000000000L + (t1.tv_nsec - t0.tv_nsec);
+    if (elapsed < req.tv_nsec) {
+        printf("Nanosleep too short: %ld ns\n", elapsed);
+        exit(-1);
+    }
+    printf("Nanosleep ok!\n");
+}
+
+void test_sched_yield(void)
+{
+    int i;
+
+    for (i = 0; i < 10; i++) {
+        if (sched_yield() != 0) {
+            printf("Sched_yield error!\n");
+            exit(-1);
+        }
+    }
+    printf("Sched_yield ok!\n");
+}
+
+void test_getrandom(void)
+{
+    unsigned char buf1[32], buf2[32];
+
+    if (getrandom(buf1, sizeof(buf1), 0) != sizeof(buf1) ||
+        getrandom(buf2, sizeof(buf2), GRND_NONBLOCK) != sizeof(buf2)) {
+        printf("Getrandom error!\n");
+        exit(-1);
+    }
+    if (memcmp(buf1, buf2, sizeof(buf1)) == 0) {
+        printf("Getrandom repeated bytes!\n");
+        exit(-1);
+    }
+    printf("Getrandom ok!\n");
+}
+
+void test_uname(void)
+{
+    struct utsname uts;
+
+    if (uname(&uts) != 0) {
+        printf("Uname error!\n");
+        exit(-1);
+    }
+    if (strcmp(uts.sysname, "ArceOS") != 0) {
+        printf("Uname sysname error: %s\n", uts.sysname);
+        exit(-1);
+    }
+    printf("Uname ok!\n");
+}
+
+void test_fstat(const char *fname)
+{
+    int fd;
+    struct stat st;
+
+    fd = open(fname, O_RDONLY);
+    if (fd < 0) {
+        printf("Open file error!\n");
+        exit(-1);
+    }
+    if (fstat(fd, &st) != 0) {
+        printf("Fstat error!\n");
+        exit(-1);
+    }
+    if (st.st_size != strlen("hello, arceos!") + 1) {
+        printf("Fstat size error: %ld\n", (long)st.st_size);
+        exit(-1);
+    }
+    close(fd);
+    printf("Fstat ok!\n");
+}
+
+void test_lseek(const char *fname)
+{
+    int fd;
+    char buf[8] = { 0 };
+
+    fd = open(fname, O_RDONLY);
+    if (fd < 0) {
+        printf("Open file error!\n");
+        exit(-1);
+    }
+    if (lseek(fd, 7, SEEK_SET) != 7) {
+        printf("Lseek error!\n");
+        exit(-1);
+    }
+    if (read(fd, buf, 6) != 6 || strcmp(buf, "arceos") != 0) {
+        printf("Lseek read error: %s\n", buf);
+        exit(-1);
+    }
+    if (lseek(fd, 0, 3) >= 0) {
+        printf("Lseek accepted a bad whence!\n");
+        exit(-1);
+    }
+    close(fd);
+    printf("Lseek ok!\n");
+}
+
+void test_dup(const char *fname)
+{
+    int fd, fd2, fd3;
+    char buf[8] = { 0 };
+
+    fd = open(fname, O_RDONLY);
+    if (fd < 0) {
+        printf("Open file error!\n");
+        exit(-1);
+    }
+    fd2 = dup(fd);
+    fd3 = dup3(fd, 20, O_CLOEXEC);
+    if (fd2 < 0 || fd3 != 20) {
+        printf("Dup error!\n");
+        exit(-1);
+    }
+    if (dup3(fd, fd, 0) >= 0) {
+        printf("Dup3 accepted the same fd!\n");
+        exit(-1);
+    }
+
+    /* All descriptors share the same file offset. */
+    if (read(fd, buf, 7) != 7 || read(fd2, buf, 3) != 3 ||
+        read(fd3, buf, 3) != 3 || strncmp(buf, "eos", 3) != 0) {
+        printf("Dup offset error!\n");
+        exit(-1);
+    }
+    close(fd3);
+    close(fd2);
+    close(fd);
+    printf("Dup ok!\n");
+}
+
+void test_pipe(void)
+{
+    int fds[2];
+    char buf[16] = { 0 };
+
+    if (pipe2(fds, O_NONBLOCK) != 0) {
+        printf("Pipe2 error!\n");
+        exit(-1);
+    }
+    if (read(fds[0], buf, sizeof(buf)) >= 0 || errno != EAGAIN) {
+        printf("Pipe2 nonblocking read error!\n");
+        exit(-1);
+    }
+    if (write(fds[1], "piped", 5) != 5) {
+        printf("Pipe2 write error!\n");
+        exit(-1);
+    }
+    if (read(fds[0], buf, sizeof(buf)) != 5 || strcmp(buf, "piped") != 0) {
+        printf("Pipe2 read error: %s\n", buf);
+        exit(-1);
+    }
+    close(fds[0]);
+    close(fds[1]);
+    printf("Pipe2 ok!\n");
+}
+
+void test_fcntl(void)
+{
+    int fds[2];
+    int fd, flags;
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    flags = fcntl(fds[0], F_GETFL);
+    if (flags < 0 || (flags & O_NONBLOCK)) {
+        printf("Fcntl F_GETFL error!\n");
+        exit(-1);
+    }
+    if (fcntl(fds[0], F_SETFL, flags | O_NONBLOCK) != 0 ||
+        !(fcntl(fds[0], F_GETFL) & O_NONBLOCK)) {
+        printf("Fcntl set O_NONBLOCK error!\n");
+        exit(-1);
+    }
+    if (fcntl(fds[0], F_SETFL, flags) != 0 ||
+        (fcntl(fds[0], F_GETFL) & O_NONBLOCK)) {
+        printf("Fcntl clear O_NONBLOCK error!\n");
+        exit(-1);
+    }
+
+    fd = fcntl(fds[0], F_DUPFD_CLOEXEC, 30);
+    if (fd < 30 || fcntl(fd, F_GETFD) != FD_CLOEXEC) {
+        printf("Fcntl F_DUPFD_CLOEXEC error!\n");
+        exit(-1);
+    }
+    if (fcntl(fd, F_SETFD, 0) != 0 || fcntl(fd, F_GETFD) != 0) {
+        printf("Fcntl F_SETFD error!\n");
+        exit(-1);
+    }
+    /* O_NONBLOCK belongs to the file, so the duplicate sees it too. */
+    if (fcntl(fds[0], F_SETFL, flags | O_NONBLOCK) != 0 ||
+        !(fcntl(fd, F_GETFL) & O_NONBLOCK)) {
+        printf("Fcntl O_NONBLOCK not shared with a dup!\n");
+        exit(-1);
+    }
+    close(fd);
+    close(fds[0]);
+    close(fds[1]);
+    printf("Fcntl ok!\n");
+}
+
+void test_readv(const char *fname)
+{
+    int fd;
+    char head[8] = { 0 }, tail[16] = { 0 };
+    struct iovec iov[2] = {
+        { head, 7 },
+        { tail, sizeof(tail) },
+    };
+
+    fd = open(fname, O_RDONLY);
+    if (fd < 0) {
+        printf("Open file error!\n");
+        exit(-1);
+    }
+    /* The second buffer is larger than the rest of the file: a short read. */
+    if (readv(fd, iov, 2) != strlen("hello, arceos!") + 1) {
+        printf("Readv error!\n");
+        exit(-1);
+    }
+    if (strcmp(head, "hello, ") != 0 || strcmp(tail, "arceos!") != 0) {
+        printf("Readv split error: [%s] [%s]\n", head, tail);
+        exit(-1);
+    }
+    close(fd);
+    printf("Readv ok!\n");
+}
+
+void test_pread(const char *fname)
+{
+    int fd;
+    char buf[8] = { 0 };
+
+    fd = open(fname, O_RDWR);
+    if (fd < 0) {
+        printf("Open file error!\n");
+        exit(-1);
+    }
+    if (pread(fd, buf, 6, 7) != 6 || strcmp(buf, "arceos") != 0) {
+        printf("Pread error: %s\n", buf);
+        exit(-1);
+    }
+    if (lseek(fd, 0, SEEK_CUR) != 0) {
+        printf("Pread moved the file position!\n");
+        exit(-1);
+    }
+    if (pwrite(fd, "H", 1, 0) != 1 || lseek(fd, 0, SEEK_CUR) != 0) {
+        printf("Pwrite error!\n");
+        exit(-1);
+    }
+    if (read(fd, buf, 5) != 5 || strncmp(buf, "Hello", 5) != 0) {
+        printf("Pwrite content error!\n");
+        exit(-1);
+    }
+    pwrite(fd, "h", 1, 0);
+    close(fd);
+    printf("Pread ok!\n");
+}
+
+void test_ftruncate(void)
+{
+    int fd;
+    struct stat st;
+    char c = 1;
+
+    fd = open("trunc_file", O_RDWR|O_CREAT, 0600);
+    if (fd < 0) {
+        printf("Create file error!\n");
+        exit(-1);
+    }
+    if (ftruncate(fd, 8192) != 0 || fstat(fd, &st) != 0 || st.st_size != 8192) {
+        printf("Ftruncate up error!\n");
+        exit(-1);
+    }
+    if (pread(fd, &c, 1, 4096) != 1 || c != 0) {
+        printf("Ftruncate zero fill error!\n");
+        exit(-1);
+    }
+    if (ftruncate(fd, 4) != 0 || fstat(fd, &st) != 0 || st.st_size != 4) {
+        printf("Ftruncate down error!\n");
+        exit(-1);
+    }
+    if (ftruncate(fd, -1) == 0) {
+        printf("Ftruncate accepted a negative length!\n");
+        exit(-1);
+    }
+    close(fd);
+    printf("Ftruncate ok!\n");
+}
+
+void test_fsync(const char *fname)
+{
+    int fd;
+
+    fd = open(fname, O_RDWR);
+    if (fd < 0) {
+        printf("Open file error!\n");
+        exit(-1);
+    }
+    if (fsync(fd) != 0) {
+        printf("Fsync error!\n");
+        exit(-1);
+    }
+    close(fd);
+    if (fsync(fd) == 0 || errno != EBADF) {
+        printf("Fsync accepted a closed fd!\n");
+        exit(-1);
+    }
+    printf("Fsync ok!\n");
+}
+
+void test_chdir()
+{
+    char buf[64];
+
+    if (chdir("/sbin") != 0) {
+        printf("Chdir error!\n");
+        exit(-1);
+    }
+    if (getcwd(buf, sizeof(buf)) == NULL || strcmp(buf, "/sbin") != 0) {
+        printf("Getcwd error!\n");
+        exit(-1);
+    }
+    if (getcwd(buf, 3) != NULL || errno != ERANGE) {
+        printf("Getcwd accepted a short buffer!\n");
+        exit(-1);
+    }
+    if (chdir("/no_such_dir") == 0 || errno != ENOENT) {
+        printf("Chdir accepted a missing directory!\n");
+        exit(-1);
+    }
+    if (chdir("/") != 0 || getcwd(buf, sizeof(buf)) == NULL || strcmp(buf, "/") != 0) {
+        printf("Chdir back error!\n");
+        exit(-1);
+    }
+    printf("Chdir ok!\n");
+}
+
+void test_mkdir()
+{
+    int fd;
+
+    if (mkdir("test_dir", 0755) != 0) {
+        printf("Mkdir error!\n");
+        exit(-1);
+    }
+    if (mkdir("test_dir", 0755) == 0 || errno != EEXIST) {
+        printf("Mkdir accepted an existing directory!\n");
+        exit(-1);
+    }
+    fd = open("test_dir/inner", O_CREAT | O_WRONLY, 0600);
+    if (fd < 0) {
+        printf("Create file in directory error!\n");
+        exit(-1);
+    }
+    close(fd);
+    if (rmdir("test_dir") == 0 || errno != ENOTEMPTY) {
+        printf("Rmdir accepted a non-empty directory!\n");
+        exit(-1);
+    }
+    if (unlink("test_dir/inner") != 0) {
+        printf("Unlink error!\n");
+        exit(-1);
+    }
+    if (unlink("test_dir/inner") == 0 || errno != ENOENT) {
+        printf("Unlink accepted a missing file!\n");
+        exit(-1);
+    }
+    if (rmdir("test_dir") != 0) {
+        printf("Rmdir error!\n");
+        exit(-1);
+    }
+    if (rmdir("test_dir") == 0 || errno != ENOENT) {
+        printf("Rmdir accepted a missing directory!\n");
+        exit(-1);
+    }
+    printf("Mkdir ok!\n");
+}
+
+void test_rename()
+{
+    int fd;
+    char buf[8];
+
+    fd = open("rename_old", O_CREAT | O_WRONLY | O_TRUNC, 0600);
+    if (fd < 0 || write(fd, "moved", 6) != 6) {
+        printf("Create rename source error!\n");
+        exit(-1);
+    }
+    close(fd);
+    fd = open("rename_busy", O_CREAT | O_WRONLY | O_TRUNC, 0600);
+    if (fd < 0) {
+        printf("Create rename target error!\n");
+        exit(-1);
+    }
+    close(fd);
+
+    if (syscall(SYS_renameat2, AT_FDCWD, "rename_old", AT_FDCWD, "rename_busy", 1) == 0
+        || errno != EEXIST) {
+        printf("Rename replaced an existing file with RENAME_NOREPLACE!\n");
+        exit(-1);
+    }
+    if (rename("rename_old", "rename_new") != 0) {
+        printf("Rename error!\n");
+        exit(-1);
+    }
+    if (open("rename_old", O_RDONLY) >= 0 || errno != ENOENT) {
+        printf("Old name still exists after rename!\n");
+        exit(-1);
+    }
+    fd = open("rename_new", O_RDONLY);
+    if (fd < 0 || read(fd, buf, 6) != 6 || strcmp(buf, "moved") != 0) {
+        printf("New name has wrong contents after rename!\n");
+        exit(-1);
+    }
+    close(fd);
+    unlink("rename_new");
+    unlink("rename_busy");
+    printf("Rename ok!\n");
+}
+
+void test_access(const char *fname)
+{
+    if (access(fname, F_OK) != 0 || access(fname, R_OK | W_OK) != 0) {
+        printf("Access error!\n");
+        exit(-1);
+    }
+    if (access("no_such_file", F_OK) == 0 || errno != ENOENT) {
+        printf("Access accepted a missing file!\n");
+        exit(-1);
+    }
+    if (access(fname, 0x10) == 0 || errno != EINVAL) {
+        printf("Access accepted an invalid mode!\n");
+        exit(-1);
+    }
+    printf("Access ok!\n");
+}
+
+void test_readlink(const char *fname)
+{
+    char buf[64];
+    ssize_t n;
+
+    memset(buf, 'x', sizeof(buf));
+    n = readlink("/proc/self/exe", buf, sizeof(buf));
+    if (n != strlen("/sbin/mapfile") || memcmp(buf, "/sbin/mapfile", n) != 0
+        || buf[n] != 'x') {
+        printf("Readlink error!\n");
+        exit(-1);
+    }
+    if (readlink(fname, buf, sizeof(buf)) >= 0 || errno != EINVAL) {
+        printf("Readlink accepted a regular file!\n");
+        exit(-1);
+    }
+    if (readlink("no_such_file", buf, sizeof(buf)) >= 0 || errno != ENOENT) {
+        printf("Readlink accepted a missing file!\n");
+        exit(-1);
+    }
+    printf("Readlink ok!\n");
+}
+
+struct linux_dirent64 {
+    unsigned long long d_ino;
+    long long d_off;
+    unsigned short d_reclen;
+    unsigned char d_type;
+    char d_name[];
+};
+
+void test_getdents()
+{
+    int fd, pos;
+    long n;
+    int seen_a = 0, seen_bb = 0;
+    char buf[512];
+    struct linux_dirent64 *d;
+
+    if (mkdir("dents_dir", 0755) != 0) {
+        printf("Mkdir for getdents error!\n");
+        exit(-1);
+    }
+    close(open("dents_dir/a", O_CREAT | O_WRONLY, 0600));
+    close(open("dents_dir/bb", O_CREAT | O_WRONLY, 0600));
+
+    fd = open("dents_dir", O_RDONLY | O_DIRECTORY);
+    if (fd < 0) {
+        printf("Open directory error!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_getdents64, fd, buf, 8) != -1 || errno != EINVAL) {
+        printf("Getdents accepted a too small buffer!\n");
+        exit(-1);
+    }
+    while ((n = syscall(SYS_getdents64, fd, buf, sizeof(buf))) > 0) {
+        for (pos = 0; pos < n; pos += d->d_reclen) {
+            d = (struct linux_dirent64 *)(buf + pos);
+            if (strcmp(d->d_name, "a") == 0 && d->d_type == DT_REG)
+                seen_a++;
+            if (strcmp(d->d_name, "bb") == 0 && d->d_type == DT_REG)
+                seen_bb++;
+        }
+    }
+    if (n != 0 || seen_a != 1 || seen_bb != 1) {
+        printf("Getdents error!\n");
+        exit(-1);
+    }
+    close(fd);
+
+    unlink("dents_dir/a");
+    unlink("dents_dir/bb");
+    rmdir("dents_dir");
+    printf("Getdents ok!\n");
+}
+
+void test_ioctl(const char *fname)
+{
+    int fd;
+    struct winsize ws;
+
+    memset(&ws, 0, sizeof(ws));
+    if (ioctl(STDOUT_FILENO, TIOCGWINSZ, &ws) != 0 || ws.ws_col == 0) {
+        printf("Ioctl TIOCGWINSZ error!\n");
+        exit(-1);
+    }
+    fd = open(fname, O_RDONLY);
+    if (fd < 0) {
+        printf("Open file error!\n");
+        exit(-1);
+    }
+    if (ioctl(fd, TIOCGWINSZ, &ws) == 0 || errno != ENOTTY) {
+        printf("Ioctl TIOCGWINSZ accepted a regular file!\n");
+        exit(-1);
+    }
+    close(fd);
+    printf("Ioctl ok!\n");
+}
+
+void test_fionbio()
+{
+    int fds[2];
+    int on = 1, off = 0, avail = -1;
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    if (ioctl(fds[0], FIONBIO, &on) != 0 || !(fcntl(fds[0], F_GETFL) & O_NONBLOCK)) {
+        printf("Ioctl FIONBIO set error!\n");
+        exit(-1);
+    }
+    if (ioctl(fds[0], FIONBIO, &off) != 0 || (fcntl(fds[0], F_GETFL) & O_NONBLOCK)) {
+        printf("Ioctl FIONBIO clear error!\n");
+        exit(-1);
+    }
+    if (write(fds[1], "abc", 3) != 3 || ioctl(fds[0], FIONREAD, &avail) != 0 || avail != 3) {
+        printf("Ioctl FIONREAD error!\n");
+        exit(-1);
+    }
+    close(fds[0]);
+    close(fds[1]);
+    if (ioctl(fds[0], FIONBIO, &on) == 0 || errno != EBADF) {
+        printf("Ioctl FIONBIO accepted a closed fd!\n");
+        exit(-1);
+    }
+    printf("Ioctl FIONBIO ok!\n");
+}
+
+void test_writev()
+{
+    int fds[2];
+    char big[200], rest[100], out[256];
+    struct iovec iov[3];
+
+    if (pipe2(fds, O_NONBLOCK) != 0) {
+        printf("Pipe2 error!\n");
+        exit(-1);
+    }
+    memset(big, 'a', sizeof(big));
+    memset(rest, 'b', sizeof(rest));
+    iov[0].iov_base = big;
+    iov[0].iov_len = sizeof(big);
+    iov[1].iov_base = NULL;
+    iov[1].iov_len = 0;
+    iov[2].iov_base = rest;
+    iov[2].iov_len = sizeof(rest);
+
+    /* the pipe only holds 256 bytes, so the last buffer is written partially */
+    if (writev(fds[1], iov, 3) != 256) {
+        printf("Writev partial write error!\n");
+        exit(-1);
+    }
+    if (writev(fds[1], iov, 3) != -1 || errno != EAGAIN) {
+        printf("Writev to a full pipe error!\n");
+        exit(-1);
+    }
+    if (read(fds[0], out, sizeof(out)) != 256 || out[199] != 'a' || out[200] != 'b'
+        || out[255] != 'b') {
+        printf("Writev wrote wrong data!\n");
+        exit(-1);
+    }
+    close(fds[0]);
+    close(fds[1]);
+    printf("Writev ok!\n");
+}
+
+#define SYS_syscall_count 1000
+#define SYS_dump_maps 1001
+#define SYS_alloc_stats 1002
+
+void test_syscall_count()
+{
+    int fds[2];
+    int i;
+    long before, after;
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    before = syscall(SYS_syscall_count, SYS_write);
+    for (i = 0; i < 5; i++)
+        write(fds[1], "x", 1);
+    after = syscall(SYS_syscall_count, SYS_write);
+    if (after - before != 5) {
+        printf("Syscall count error: %ld\n", after - before);
+        exit(-1);
+    }
+    close(fds[0]);
+    close(fds[1]);
+    printf("Syscall count ok!\n");
+}
+
+void test_enosys()
+{
+    if (syscall(999) != -1 || errno != ENOSYS) {
+        printf("Unregistered syscall did not fail with ENOSYS!\n");
+        exit(-1);
+    }
+    printf("ENOSYS ok!\n");
+}
+
+void test_rlimit()
+{
+    struct rlimit rl, saved;
+
+    if (getrlimit(RLIMIT_NOFILE, &saved) != 0 || saved.rlim_cur != 1024
+        || saved.rlim_max != 1024) {
+        printf("Getrlimit RLIMIT_NOFILE error!\n");
+        exit(-1);
+    }
+    rl.rlim_cur = 64;
+    rl.rlim_max = 1024;
+    if (setrlimit(RLIMIT_NOFILE, &rl) != 0 || getrlimit(RLIMIT_NOFILE, &rl) != 0
+        || rl.rlim_cur != 64) {
+        printf("Setrlimit RLIMIT_NOFILE error!\n");
+        exit(-1);
+    }
+    rl.rlim_max = 4096;
+    if (setrlimit(RLIMIT_NOFILE, &rl) == 0 || errno != EPERM) {
+        printf("Setrlimit raised RLIMIT_NOFILE above the fd table size!\n");
+        exit(-1);
+    }
+    if (setrlimit(RLIMIT_NOFILE, &saved) != 0) {
+        printf("Setrlimit restore error!\n");
+        exit(-1);
+    }
+    if (getrlimit(RLIMIT_STACK, &rl) != 0 || rl.rlim_cur == 0) {
+        printf("Getrlimit RLIMIT_STACK error!\n");
+        exit(-1);
+    }
+    if (getrlimit(RLIMIT_NLIMITS + 1, &rl) == 0 || errno != EINVAL) {
+        printf("Getrlimit accepted an unknown resource!\n");
+        exit(-1);
+    }
+    printf("Rlimit ok!\n");
+}
+
+void test_getuid()
+{
+    if (getuid() != 0 || geteuid() != 0 || getgid() != 0 || getegid() != 0) {
+        printf("Getuid error!\n");
+        exit(-1);
+    }
+    printf("Getuid ok!\n");
+}
+
+void test_clock_nanosleep(void)
+{
+    struct timespec deadline, t0, t1;
+    long elapsed;
+
+    clock_gettime(CLOCK_MONOTONIC, &t0);
+    deadline = t0;
+    deadline.tv_sec -= 1;
+    if (clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &deadline, NULL) != 0) {
+        printf("Clock_nanosleep with a past deadline error!\n");
+        exit(-1);
+    }
+    clock_gettime(CLOCK_MONOTONIC, &t1);
+    elapsed = (t1.tv_sec - t0.tv_sec) * 1000000000L + (t1.tv_nsec - t0.tv_nsec);
+    if (elapsed > 5 * 1000 * 1000) {
+        printf("Clock_nanosleep with a past deadline blocked: %ld ns\n", elapsed);
+        exit(-1);
+    }
+
+    deadline = t1;
+    deadline.tv_nsec += 10 * 1000 * 1000;
+    if (deadline.tv_nsec >= 1000000000L) {
+        deadline.tv_sec += 1;
+        deadline.tv_nsec -= 1000000000L;
+    }
+    if (clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &deadline, NULL) != 0) {
+        printf("Clock_nanosleep with a future deadline error!\n");
+        exit(-1);
+    }
+    clock_gettime(CLOCK_MONOTONIC, &t1);
+    if (t1.tv_sec < deadline.tv_sec
+        || (t1.tv_sec == deadline.tv_sec && t1.tv_nsec < deadline.tv_nsec)) {
+        printf("Clock_nanosleep woke before the deadline!\n");
+        exit(-1);
+    }
+    printf("Clock_nanosleep ok!\n");
+}
+
+void test_times(void)
+{
+    struct tms t0, t1;
+    clock_t c0, c1;
+    volatile unsigned long spin = 0;
+    unsigned long i;
+
+    c0 = times(&t0);
+    for (i = 0; i < 50000000UL; i++) {
+        spin++;
+    }
+    c1 = times(&t1);
+    if (c0 == (clock_t)-1 || c1 == (clock_t)-1) {
+        printf("Times error!\n");
+        exit(-1);
+    }
+    if (c1 < c0 || t1.tms_utime < t0.tms_utime || t1.tms_stime < t0.tms_stime) {
+        printf("Times went backwards!\n");
+        exit(-1);
+    }
+    printf("Times ok!\n");
+}
+
+void test_sysinfo(void)
+{
+    struct sysinfo info;
+
+    if (sysinfo(&info) != 0) {
+        printf("Sysinfo error!\n");
+        exit(-1);
+    }
+    if (info.mem_unit == 0 || info.freeram == 0 || info.totalram < info.freeram) {
+        printf("Sysinfo bad memory stats: total %lu, free %lu, unit %u\n",
+               info.totalram, info.freeram, info.mem_unit);
+        exit(-1);
+    }
+    printf("Sysinfo ok!\n");
+}
+
+void test_mlock(void)
+{
+    char *addr;
+
+    addr = mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    if (mlock(addr + 100, 4096) != 0) {
+        printf("Mlock error!\n");
+        exit(-1);
+    }
+    if (munlock(addr, 8192) != 0) {
+        printf("Munlock error!\n");
+        exit(-1);
+    }
+    /* The first pages of user space are never mapped. */
+    if (mlock((void *)0x1000, 4096) != -1 || errno != ENOMEM) {
+        printf("Mlock on an unmapped range should fail with ENOMEM!\n");
+        exit(-1);
+    }
+    printf("Mlock ok!\n");
+}
+
+void test_statfs(const char *fname)
+{
+    struct statfs st;
+    int fd;
+
+    if (statfs("/", &st) != 0) {
+        printf("Statfs error!\n");
+        exit(-1);
+    }
+    if (st.f_bsize <= 0 || (st.f_bsize & (st.f_bsize - 1)) != 0 || st.f_type == 0) {
+        printf("Statfs bad result: type %#lx, bsize %ld\n", (long)st.f_type, (long)st.f_bsize);
+        exit(-1);
+    }
+    fd = open(fname, O_RDONLY);
+    if (fd < 0 || fstatfs(fd, &st) != 0 || (st.f_bsize & (st.f_bsize - 1)) != 0) {
+        printf("Fstatfs error!\n");
+        exit(-1);
+    }
+    close(fd);
+    if (statfs("/no_such_dir", &st) != -1 || errno != ENOENT) {
+        printf("Statfs on a missing path should fail with ENOENT!\n");
+        exit(-1);
+    }
+    if (fstatfs(-1, &st) != -1 || errno != EBADF) {
+        printf("Fstatfs on a bad fd should fail with EBADF!\n");
+        exit(-1);
+    }
+    printf("Statfs ok!\n");
+}
+
+void test_umask(void)
+{
+    const char *path = "umask_file";
+    const char *dir = "umask_dir";
+    struct stat st;
+    mode_t old;
+    int fd;
+
+    old = umask(022);
+    if (umask(022) != 022) {
+        printf("Umask did not return the previous mask!\n");
+        exit(-1);
+    }
+    fd = open(path, O_RDWR | O_CREAT | O_TRUNC, 0666);
+    if (fd < 0 || fstat(fd, &st) != 0) {
+        printf("Umask create error!\n");
+        exit(-1);
+    }
+    close(fd);
+    unlink(path);
+    if (st.st_mode & 022) {
+        printf("Umask not applied: mode %o\n", st.st_mode & 0777);
+        exit(-1);
+    }
+    /* The kernel has no stat syscall, so stat the directory through an fd. */
+    if (mkdir(dir, 0777) != 0) {
+        printf("Umask mkdir error!\n");
+        exit(-1);
+    }
+    fd = open(dir, O_RDONLY | O_DIRECTORY);
+    if (fd < 0 || fstat(fd, &st) != 0) {
+        printf("Umask stat directory error!\n");
+        exit(-1);
+    }
+    close(fd);
+    rmdir(dir);
+    if (!S_ISDIR(st.st_mode) || (st.st_mode & 0777) != 0755) {
+        printf("Umask not applied to mkdir: mode %o\n", st.st_mode & 0777);
+        exit(-1);
+    }
+    umask(old);
+    printf("Umask ok!\n");
+}
+
+struct clone_shared {
+    volatile int value;
+    volatile unsigned long tp;
+};
+
+static int clone_child(void *arg)
+{
+    struct clone_shared *shared = arg;
+    unsigned long tp;
+
+    __asm__ volatile("mv %0, tp" : "=r"(tp));
+    shared->tp = tp;
+    shared->value = 42;
+    return 0;
+}
+
+void test_clone(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    static char tls[64] __attribute__((aligned(16)));
+    static struct clone_shared shared;
+    static volatile pid_t ctid;
+    static pid_t ptid;
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD
+        | CLONE_SYSVSEM | CLONE_SETTLS | CLONE_PARENT_SETTID | CLONE_CHILD_SETTID
+        | CLONE_CHILD_CLEARTID;
+    pid_t seen;
+    int tid;
+
+    tid = clone(clone_child, stack + sizeof(stack), flags, &shared,
+                &ptid, tls, (pid_t *)&ctid);
+    if (tid <= 0 || ptid != tid || tid == syscall(SYS_gettid)) {
+        printf("Clone error: tid %d, ptid %d\n", tid, ptid);
+        exit(-1);
+    }
+    /*
+     * CLONE_CHILD_CLEARTID zeroes ctid and wakes a futex waiter on it when
+     * the child exits, which is how pthread_join waits.
+     */
+    while ((seen = ctid) != 0) {
+        if (syscall(SYS_futex, &ctid, FUTEX_WAIT, seen, NULL, NULL, 0) != 0 && errno != EAGAIN) {
+            printf("Clone futex wait on ctid error!\n");
+            exit(-1);
+        }
+    }
+    if (shared.value != 42) {
+        printf("Clone child did not write the shared memory!\n");
+        exit(-1);
+    }
+    if (shared.tp != (unsigned long)tls) {
+        printf("Clone child got the wrong TLS: %#lx\n", shared.tp);
+        exit(-1);
+    }
+    printf("Clone ok!\n");
+}
+
+static int wait_child(void *arg)
+{
+    (void)arg;
+    return 7;
+}
+
+void test_wait4(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    int status = 0;
+    int tid;
+
+    tid = clone(wait_child, stack + sizeof(stack), flags, NULL);
+    if (tid <= 0) {
+        printf("Wait4 clone error!\n");
+        exit(-1);
+    }
+    if (wait4(tid, &status, __WALL, NULL) != tid) {
+        printf("Wait4 error!\n");
+        exit(-1);
+    }
+    if (!WIFEXITED(status) || WEXITSTATUS(status) != 7) {
+        printf("Wait4 bad status: %#x\n", status);
+        exit(-1);
+    }
+    if (wait4(tid, &status, WNOHANG | __WALL, NULL) != -1 || errno != ECHILD) {
+        printf("Wait4 on a reaped child should fail with ECHILD!\n");
+        exit(-1);
+    }
+    printf("Wait4 ok!\n");
+}
+
+/* Never enters the kernel by itself, so only a trap can deliver SIGKILL. */
+static int spin_child(void *arg)
+{
+    volatile unsigned long *counter = arg;
+
+    for (;;) {
+        (*counter)++;
+    }
+    return 0;
+}
+
+void test_kill(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    static volatile unsigned long counter;
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    int status = 0;
+    int tid;
+
+    if (kill(getpid(), 0) != 0) {
+        printf("Kill probe error!\n");
+        exit(-1);
+    }
+    if (kill(getpid(), 65) != -1 || errno != EINVAL) {
+        printf("Kill with a bad signal should fail with EINVAL!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_tkill, 999999, SIGTERM) != -1 || errno != ESRCH) {
+        printf("Tkill of a missing thread should fail with ESRCH!\n");
+        exit(-1);
+    }
+
+    tid = clone(spin_child, stack + sizeof(stack), flags, (void *)&counter);
+    if (tid <= 0) {
+        printf("Kill clone error!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_tkill, tid, SIGKILL) != 0) {
+        printf("Tkill error!\n");
+        exit(-1);
+    }
+    if (wait4(tid, &status, __WALL, NULL) != tid) {
+        printf("Kill wait error!\n");
+        exit(-1);
+    }
+    if (!WIFSIGNALED(status) || WTERMSIG(status) != SIGKILL) {
+        printf("Killed child bad status: %#x\n", status);
+        exit(-1);
+    }
+    printf("Kill ok!\n");
+}
+
+static void dummy_handler(int sig)
+{
+    (void)sig;
+}
+
+void test_sigaction(void)
+{
+    struct sigaction sa, old;
+    sigset_t set, oldset;
+
+    memset(&sa, 0, sizeof(sa));
+    sa.sa_handler = dummy_handler;
+    sigemptyset(&sa.sa_mask);
+    sigaddset(&sa.sa_mask, SIGUSR2);
+    if (sigaction(SIGUSR1, &sa, NULL) != 0) {
+        printf("Sigaction error!\n");
+        exit(-1);
+    }
+    if (sigaction(SIGUSR1, NULL, &old) != 0 || old.sa_handler != dummy_handler
+        || !sigismember(&old.sa_mask, SIGUSR2)) {
+        printf("Sigaction did not read back the handler!\n");
+        exit(-1);
+    }
+    if (sigaction(SIGKILL, &sa, NULL) != -1 || errno != EINVAL) {
+        printf("Sigaction on SIGKILL should fail with EINVAL!\n");
+        exit(-1);
+    }
+    sa.sa_handler = SIG_DFL;
+    sigaction(SIGUSR1, &sa, NULL);
+
+    sigemptyset(&set);
+    sigaddset(&set, SIGUSR1);
+    if (sigprocmask(SIG_BLOCK, &set, NULL) != 0) {
+        printf("Sigprocmask error!\n");
+        exit(-1);
+    }
+    if (sigprocmask(SIG_SETMASK, NULL, &oldset) != 0 || !sigismember(&oldset, SIGUSR1)) {
+        printf("Sigprocmask did not read back the mask!\n");
+        exit(-1);
+    }
+    sigprocmask(SIG_UNBLOCK, &set, NULL);
+    printf("Sigaction ok!\n");
+}
+
+void test_bad_buffer(const char *fname)
+{
+    char *ro;
+    int fd;
+
+    fd = open(fname, O_RDONLY);
+    if (fd < 0) {
+        printf("Bad buffer open error!\n");
+        exit(-1);
+    }
+    if (read(fd, (void *)0x1000, 16) != -1 || errno != EFAULT) {
+        printf("Read into an unmapped buffer should fail with EFAULT!\n");
+        exit(-1);
+    }
+    ro = mmap(NULL, 4096, PROT_READ, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (ro == MAP_FAILED) {
+        printf("Map read-only error!\n");
+        exit(-1);
+    }
+    if (read(fd, ro, 16) != -1 || errno != EFAULT) {
+        printf("Read into a read-only buffer should fail with EFAULT!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_getcwd, ro, 64) != -1 || errno != EFAULT) {
+        printf("Getcwd into a read-only buffer should fail with EFAULT!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_fstat, fd, ro) != -1 || errno != EFAULT) {
+        printf("Fstat into a read-only buffer should fail with EFAULT!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_clock_gettime, CLOCK_MONOTONIC, ro) != -1 || errno != EFAULT) {
+        printf("Clock_gettime into a read-only buffer should fail with EFAULT!\n");
+        exit(-1);
+    }
+    close(fd);
+    if (write(STDOUT_FILENO, (void *)0x1000, 16) != -1 || errno != EFAULT) {
+        printf("Write from an unmapped buffer should fail with EFAULT!\n");
+        exit(-1);
+    }
+    printf("Bad buffer ok!\n");
+}
+
+void test_copy_user(void)
+{
+    struct rlimit saved, *rl;
+    struct utsname local, *uts;
+    char *pages, *ro;
+
+    if (getrlimit(RLIMIT_STACK, &saved) != 0) {
+        printf("Copy user getrlimit error!\n");
+        exit(-1);
+    }
+    /* Put the struct across a page boundary. */
+    pages = mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (pages == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    rl = (struct rlimit *)(pages + 4096 - sizeof(rl->rlim_cur));
+    rl->rlim_cur = 0x12345;
+    rl->rlim_max = RLIM_INFINITY;
+    /* The new limit is read before the old one is written back in place. */
+    if (prlimit(0, RLIMIT_STACK, rl, rl) != 0
+        || rl->rlim_cur != saved.rlim_cur || rl->rlim_max != saved.rlim_max) {
+        printf("Copy user did not return the old limit!\n");
+        exit(-1);
+    }
+    if (prlimit(0, RLIMIT_STACK, NULL, rl) != 0
+        || rl->rlim_cur != 0x12345 || rl->rlim_max != RLIM_INFINITY) {
+        printf("Copy user did not round-trip the new limit!\n");
+        exit(-1);
+    }
+    if (prlimit(0, RLIMIT_STACK, (struct rlimit *)0x1000, NULL) != -1 || errno != EFAULT) {
+        printf("Copy user from an unmapped address should fail with EFAULT!\n");
+        exit(-1);
+    }
+    ro = mmap(NULL, 4096, PROT_READ, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (ro == MAP_FAILED) {
+        printf("Copy user map read-only error!\n");
+        exit(-1);
+    }
+    if (prlimit(0, RLIMIT_STACK, NULL, (struct rlimit *)ro) != -1 || errno != EFAULT) {
+        printf("Copy user to a read-only page should fail with EFAULT!\n");
+        exit(-1);
+    }
+    setrlimit(RLIMIT_STACK, &saved);
+
+    /* A larger struct copied out across the same boundary. */
+    uts = (struct utsname *)(pages + 4096 - sizeof(uts->sysname));
+    if (uname(&local) != 0 || uname(uts) != 0
+        || memcmp(&local, uts, sizeof(local)) != 0) {
+        printf("Copy user did not copy utsname across pages!\n");
+        exit(-1);
+    }
+    munmap(ro, 4096);
+    munmap(pages, 8192);
+    printf("Copy user ok!\n");
+}
+
+void test_user_path(const char *fname)
+{
+    char *pages;
+    char *path;
+    int fd;
+
+    pages = mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (pages == MAP_FAILED) {
+        printf("User path map error!\n");
+        exit(-1);
+    }
+    /* A path split across two pages is read in full. */
+    path = pages + 4096 - 4;
+    strcpy(path, fname);
+    fd = open(path, O_RDONLY);
+    if (fd < 0) {
+        printf("User path across pages should open!\n");
+        exit(-1);
+    }
+    close(fd);
+    /* No NUL within PATH_MAX bytes. */
+    memset(pages, 'a', 8192);
+    if (open(pages, O_RDONLY) != -1 || errno != ENAMETOOLONG) {
+        printf("User path without a NUL should fail with ENAMETOOLONG!\n");
+        exit(-1);
+    }
+    /* A path running into an unmapped page. */
+    munmap(pages + 4096, 4096);
+    if (open(pages + 4096 - 16, O_RDONLY) != -1 || errno != EFAULT) {
+        printf("User path into an unmapped page should fail with EFAULT!\n");
+        exit(-1);
+    }
+    munmap(pages, 4096);
+    printf("User path ok!\n");
+}
+
+void test_maps(void)
+{
+    static char dump[4096];
+    char line[40];
+    char *a, *b;
+    long len;
+
+    a = mmap(NULL, 4096, PROT_READ, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    b = mmap(NULL, 8192, PROT_READ|PROT_WRITE, MAP_SHARED|MAP_ANONYMOUS, -1, 0);
+    if (a == MAP_FAILED || b == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    len = syscall(SYS_dump_maps, dump, sizeof(dump) - 1);
+    if (len <= 0) {
+        printf("Dump maps error!\n");
+        exit(-1);
+    }
+    dump[len] = '\0';
+    snprintf(line, sizeof(line), "%016lx-%016lx r--p", (unsigned long)a, (unsigned long)a + 4096);
+    if (strstr(dump, line) == NULL) {
+        printf("Dump maps misses the first region!\n");
+        exit(-1);
+    }
+    snprintf(line, sizeof(line), "%016lx-%016lx rw-s", (unsigned long)b, (unsigned long)b + 8192);
+    if (strstr(dump, line) == NULL) {
+        printf("Dump maps misses the second region!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_dump_maps, dump, 1) != -1 || errno != ERANGE) {
+        printf("Dump maps into a short buffer should fail with ERANGE!\n");
+        exit(-1);
+    }
+    printf("Maps ok!\n");
+}
+
+struct alloc_stats {
+    unsigned long used;
+    unsigned long free;
+    unsigned long peak;
+};
+
+void test_alloc_stats(void)
+{
+    const unsigned long size = 64 * 4096;
+    struct alloc_stats before, mapped, freed;
+    char *addr;
+
+    if (syscall(SYS_alloc_stats, &before) != 0) {
+        printf("Alloc stats error!\n");
+        exit(-1);
+    }
+    addr = mmap(NULL, size, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    syscall(SYS_alloc_stats, &mapped);
+    /* Leave some slack for kernel allocations freed in between. */
+    if (mapped.used < before.used + size - 4 * 4096 || mapped.peak < mapped.used) {
+        printf("Alloc stats did not count the mapping: used %lu -> %lu, peak %lu\n",
+               before.used, mapped.used, mapped.peak);
+        exit(-1);
+    }
+    /* Shrinking the mapping gives its frames back. */
+    if (mremap(addr, size, 4096, 0) != addr) {
+        printf("Mremap shrink error!\n");
+        exit(-1);
+    }
+    syscall(SYS_alloc_stats, &freed);
+    if (freed.used + size - 8 * 4096 > mapped.used || freed.free <= mapped.free
+        || freed.peak < mapped.used) {
+        printf("Alloc stats did not count the release: used %lu -> %lu, peak %lu\n",
+               mapped.used, freed.used, freed.peak);
+        exit(-1);
+    }
+    if (syscall(SYS_alloc_stats, (void *)0x1000) != -1 || errno != EFAULT) {
+        printf("Alloc stats to an unmapped address should fail with EFAULT!\n");
+        exit(-1);
+    }
+    printf("Alloc stats ok!\n");
+}
+
+static int pipe_reader(void *arg)
+{
+    int fd = *(int *)arg;
+    char c;
+
+    /* Blocks on the empty pipe until the thread is killed. */
+    read(fd, &c, 1);
+    return 0;
+}
+
+void test_short_read(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    char buf[16] = { 0 }, more[16] = { 0 };
+    struct iovec iov[2];
+    int fds[2];
+    int status = 0;
+    int tid;
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    if (write(fds[1], "short", 5) != 5) {
+        printf("Pipe write error!\n");
+        exit(-1);
+    }
+    if (read(fds[0], buf, sizeof(buf)) != 5 || strcmp(buf, "short") != 0) {
+        printf("Short pipe read should return the partial count!\n");
+        exit(-1);
+    }
+    if (write(fds[1], "vector", 6) != 6) {
+        printf("Pipe write error!\n");
+        exit(-1);
+    }
+    iov[0].iov_base = buf;
+    iov[0].iov_len = 4;
+    iov[1].iov_base = more;
+    iov[1].iov_len = sizeof(more);
+    if (readv(fds[0], iov, 2) != 6 || memcmp(buf, "vect", 4) != 0 || strcmp(more, "or") != 0) {
+        printf("Short pipe readv should return the partial count!\n");
+        exit(-1);
+    }
+
+    tid = clone(pipe_reader, stack + sizeof(stack), flags, &fds[0]);
+    if (tid <= 0) {
+        printf("Short read clone error!\n");
+        exit(-1);
+    }
+    /* Let the reader block first. */
+    for (int i = 0; i < 10; i++) {
+        sched_yield();
+    }
+    if (syscall(SYS_tkill, tid, SIGKILL) != 0 || wait4(tid, &status, __WALL, NULL) != tid) {
+        printf("Blocked pipe reader was not interrupted!\n");
+        exit(-1);
+    }
+    close(fds[0]);
+    close(fds[1]);
+    printf("Short read ok!\n");
+}
+
+static int pipe_writer(void *arg)
+{
+    int fd = *(int *)arg;
+
+    for (int i = 0; i < 10; i++) {
+        sched_yield();
+    }
+    write(fd, "x", 1);
+    return 0;
+}
+
+void test_poll(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    struct pollfd pfd;
+    int fds[2];
+    int status = 0;
+    int tid;
+    char c;
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    pfd.fd = fds[0];
+    pfd.events = POLLIN;
+    if (poll(&pfd, 1, 0) != 0 || pfd.revents != 0) {
+        printf("Poll of an empty pipe should not be ready!\n");
+        exit(-1);
+    }
+    pfd.fd = fds[1];
+    pfd.events = POLLOUT;
+    if (poll(&pfd, 1, 0) != 1 || pfd.revents != POLLOUT) {
+        printf("Poll of a pipe write end should be writable!\n");
+        exit(-1);
+    }
+    pfd.fd = STDOUT_FILENO;
+    if (poll(&pfd, 1, 0) != 1 || !(pfd.revents & POLLOUT)) {
+        printf("Poll of the console should be writable!\n");
+        exit(-1);
+    }
+    /* Without fds, poll only sleeps and never touches the array. */
+    if (poll(NULL, 0, 10) != 0) {
+        printf("Poll of no fds should time out!\n");
+        exit(-1);
+    }
+    if (poll((struct pollfd *)0x1000, 1, 0) != -1 || errno != EFAULT) {
+        printf("Poll of an unmapped array should fail with EFAULT!\n");
+        exit(-1);
+    }
+
+    tid = clone(pipe_writer, stack + sizeof(stack), flags, &fds[1]);
+    if (tid <= 0) {
+        printf("Poll clone error!\n");
+        exit(-1);
+    }
+    pfd.fd = fds[0];
+    pfd.events = POLLIN;
+    if (poll(&pfd, 1, 5000) != 1 || pfd.revents != POLLIN) {
+        printf("Poll did not see the pipe become readable!\n");
+        exit(-1);
+    }
+    if (read(fds[0], &c, 1) != 1 || c != 'x') {
+        printf("Poll read error!\n");
+        exit(-1);
+    }
+    wait4(tid, &status, __WALL, NULL);
+    close(fds[0]);
+    close(fds[1]);
+    printf("Poll ok!\n");
+}
+
+void test_clock_getres(void)
+{
+    struct timespec res;
+
+    if (clock_getres(CLOCK_MONOTONIC, &res) != 0 || res.tv_sec != 0
+        || res.tv_nsec <= 0 || res.tv_nsec >= 1000000000) {
+        printf("Clock_getres monotonic error!\n");
+        exit(-1);
+    }
+    if (clock_getres(CLOCK_REALTIME, &res) != 0 || res.tv_sec != 0 || res.tv_nsec <= 0) {
+        printf("Clock_getres realtime error!\n");
+        exit(-1);
+    }
+    if (clock_getres(CLOCK_PROCESS_CPUTIME_ID, &res) != -1 || errno != EINVAL) {
+        printf("Clock_getres of an unsupported clock should fail with EINVAL!\n");
+        exit(-1);
+    }
+    printf("Clock_getres ok!\n");
+}
+
+void test_open_mode(void)
+{
+    const char *path = "mode_file";
+    struct stat st;
+    mode_t old;
+    int fd;
+
+    old = umask(027);
+    unlink(path);
+    fd = open(path, O_RDWR | O_CREAT, 0666);
+    if (fd < 0 || fstat(fd, &st) != 0 || (st.st_mode & 0777) != 0640) {
+        printf("Open with O_CREAT did not apply the mode: %o\n", st.st_mode & 0777);
+        exit(-1);
+    }
+    close(fd);
+    /* The mode of an existing file is left alone. */
+    fd = open(path, O_RDWR | O_CREAT, 0600);
+    if (fd < 0 || fstat(fd, &st) != 0 || (st.st_mode & 0777) != 0640) {
+        printf("Open of an existing file changed its mode: %o\n", st.st_mode & 0777);
+        exit(-1);
+    }
+    close(fd);
+    /* Without O_CREAT the mode is ignored, whatever it holds. */
+    fd = syscall(SYS_openat, AT_FDCWD, path, O_RDONLY, 0xffffffff);
+    if (fd < 0) {
+        printf("Open without O_CREAT should ignore the mode!\n");
+        exit(-1);
+    }
+    close(fd);
+    /* The mode follows the file across a rename. */
+    if (rename(path, "./mode_moved") != 0) {
+        printf("Open mode rename error!\n");
+        exit(-1);
+    }
+    fd = open("mode_moved", O_RDONLY);
+    if (fd < 0 || fstat(fd, &st) != 0 || (st.st_mode & 0777) != 0640) {
+        printf("Rename lost the mode of the file: %o\n", st.st_mode & 0777);
+        exit(-1);
+    }
+    close(fd);
+    unlink("mode_moved");
+    umask(old);
+    printf("Open mode ok!\n");
+}
+
+void test_openat(const char *fname)
+{
+    char buf[8] = { 0 };
+    int dfd, fd;
+
+    if (mkdir("at_dir", 0755) != 0) {
+        printf("Mkdir for openat error!\n");
+        exit(-1);
+    }
+    dfd = open("at_dir", O_RDONLY | O_DIRECTORY);
+    if (dfd < 0) {
+        printf("Open directory error!\n");
+        exit(-1);
+    }
+    fd = openat(dfd, "f", O_CREAT | O_RDWR, 0644);
+    if (fd < 0 || write(fd, "at", 2) != 2) {
+        printf("Openat relative to a directory fd error!\n");
+        exit(-1);
+    }
+    close(fd);
+    fd = open("at_dir/f", O_RDONLY);
+    if (fd < 0 || read(fd, buf, sizeof(buf)) != 2 || strcmp(buf, "at") != 0) {
+        printf("Openat created the file in the wrong place!\n");
+        exit(-1);
+    }
+    close(fd);
+
+    fd = open(fname, O_RDONLY);
+    if (openat(fd, "f", O_RDONLY) != -1 || errno != ENOTDIR) {
+        printf("Openat relative to a file should fail with ENOTDIR!\n");
+        exit(-1);
+    }
+    close(fd);
+    if (openat(999, "f", O_RDONLY) != -1 || errno != EBADF) {
+        printf("Openat relative to a closed fd should fail with EBADF!\n");
+        exit(-1);
+    }
+
+    unlinkat(dfd, "f", 0);
+    close(dfd);
+    rmdir("at_dir");
+    printf("Openat ok!\n");
+}
+
+void test_close(void)
+{
+    int fds[2];
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    if (close(fds[0]) != 0) {
+        printf("Close error!\n");
+        exit(-1);
+    }
+    if (close(fds[0]) != -1 || errno != EBADF) {
+        printf("Closing an fd twice should fail with EBADF!\n");
+        exit(-1);
+    }
+    if (close(-1) != -1 || errno != EBADF) {
+        printf("Closing a negative fd should fail with EBADF!\n");
+        exit(-1);
+    }
+    close(fds[1]);
+    printf("Close ok!\n");
+}
+
+static int exit_child(void *arg)
+{
+    syscall(SYS_exit, *(int *)arg);
+    return 0;
+}
+
+void test_exit_status(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    int codes[] = { 256, 257 };
+    int status, tid;
+
+    for (int i = 0; i < 2; i++) {
+        tid = clone(exit_child, stack + sizeof(stack), flags, &codes[i]);
+        if (tid <= 0 || wait4(tid, &status, __WALL, NULL) != tid) {
+            printf("Exit status clone error!\n");
+            exit(-1);
+        }
+        if (!WIFEXITED(status) || WEXITSTATUS(status) != (codes[i] & 0xff)) {
+            printf("Exit code %d seen as %d!\n", codes[i], WEXITSTATUS(status));
+            exit(-1);
+        }
+    }
+    printf("Exit status ok!\n");
+}
+
+void test_membarrier(void)
+{
+    long cmds = syscall(SYS_membarrier, 0 /* MEMBARRIER_CMD_QUERY */, 0, 0);
+
+    if (cmds <= 0 || !(cmds & 1 /* MEMBARRIER_CMD_GLOBAL */)) {
+        printf("Membarrier query error: %ld\n", cmds);
+        exit(-1);
+    }
+    if (syscall(SYS_membarrier, 1, 0, 0) != 0) {
+        printf("Membarrier global error!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_membarrier, 1 << 30, 0, 0) != -1 || errno != EINVAL) {
+        printf("Membarrier with an unknown command should fail with EINVAL!\n");
+        exit(-1);
+    }
+    printf("Membarrier ok!\n");
+}
+
+void test_mmap_fd_checks(void)
+{
+    const char *path = "mmap_offset";
+    static char page[4096];
+    int fds[2];
+    char *addr;
+    int fd;
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    if (mmap(NULL, 4096, PROT_READ, MAP_PRIVATE, fds[0], 0) != MAP_FAILED || errno != ENODEV) {
+        printf("Mmap of a pipe should fail with ENODEV!\n");
+        exit(-1);
+    }
+    close(fds[0]);
+    close(fds[1]);
+
+    fd = open(path, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    memset(page, 'a', sizeof(page));
+    if (fd < 0 || write(fd, page, sizeof(page)) != sizeof(page)) {
+        printf("Mmap offset file error!\n");
+        exit(-1);
+    }
+    memset(page, 'b', sizeof(page));
+    write(fd, page, sizeof(page));
+    if (syscall(SYS_mmap, NULL, 4096, PROT_READ, MAP_PRIVATE, fd, -4096) != -1 || errno != EINVAL) {
+        printf("Mmap with a negative offset should fail with EINVAL!\n");
+        exit(-1);
+    }
+    addr = mmap(NULL, 4096, PROT_READ, MAP_PRIVATE, fd, 4096);
+    if (addr == MAP_FAILED || addr[0] != 'b' || addr[4095] != 'b') {
+        printf("Mmap with an offset error!\n");
+        exit(-1);
+    }
+    close(fd);
+    unlink(path);
+    printf("Mmap fd checks ok!\n");
+}
+
+void test_map_populate(void)
+{
+    unsigned char vec[16];
+    char *addr;
+
+    addr = mmap(NULL, sizeof(vec) * 4096, PROT_READ|PROT_WRITE,
+                MAP_PRIVATE|MAP_ANONYMOUS|MAP_POPULATE, -1, 0);
+    if (addr == MAP_FAILED) {
+        printf("Map populate error!\n");
+        exit(-1);
+    }
+    memset(vec, 0, sizeof(vec));
+    if (mincore(addr, sizeof(vec) * 4096, vec) != 0) {
+        printf("Mincore error!\n");
+        exit(-1);
+    }
+    for (int i = 0; i < (int)sizeof(vec); i++) {
+        if (!(vec[i] & 1)) {
+            printf("Map populate left page %d out!\n", i);
+            exit(-1);
+        }
+    }
+    if (mincore(addr + 1, 4096, vec) != -1 || errno != EINVAL) {
+        printf("Mincore of an unaligned address should fail with EINVAL!\n");
+        exit(-1);
+    }
+    printf("Map populate ok!\n");
+}
+
+void test_map_hugetlb(void)
+{
+    const unsigned long huge = 2UL << 20;
+    char *addr;
+
+    addr = mmap(NULL, huge, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS|MAP_HUGETLB, -1, 0);
+    if (addr == MAP_FAILED || (unsigned long)addr % huge != 0) {
+        printf("Map hugetlb error: %p\n", addr);
+        exit(-1);
+    }
+    addr[0] = 'h';
+    addr[huge - 1] = 't';
+    if (addr[0] != 'h' || addr[huge - 1] != 't') {
+        printf("Map hugetlb region is not usable!\n");
+        exit(-1);
+    }
+    if (mmap(NULL, huge + 4096, PROT_READ, MAP_PRIVATE|MAP_ANONYMOUS|MAP_HUGETLB, -1, 0) != MAP_FAILED
+        || errno != EINVAL) {
+        printf("Map hugetlb of an unaligned length should fail with EINVAL!\n");
+        exit(-1);
+    }
+    printf("Map hugetlb ok!\n");
+}
+
+static void check_zeroed(const char *addr, unsigned long size, const char *what)
+{
+    for (unsigned long i = 0; i < size; i++) {
+        if (addr[i] != 0) {
+            printf("%s: byte %lu is %#x, not zero!\n", what, i, addr[i]);
+            exit(-1);
+        }
+    }
+}
+
+void test_mmap_zeroed(void)
+{
+    const unsigned long size = 8 * 4096;
+    char *addr;
+
+    addr = mmap(NULL, size, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    check_zeroed(addr, size, "Fresh anonymous mapping");
+    /* Dirty the pages and give all but one back, so the next mapping is
+     * likely to reuse their frames. */
+    memset(addr, 0x5a, size);
+    if (mremap(addr, size, 4096, 0) != addr) {
+        printf("Mremap shrink error!\n");
+        exit(-1);
+    }
+    addr = mmap(NULL, size, PROT_READ|PROT_WRITE, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0);
+    if (addr == MAP_FAILED) {
+        printf("Map anonymous error!\n");
+        exit(-1);
+    }
+    check_zeroed(addr, size, "Reused anonymous mapping");
+    printf("Mmap zeroed ok!\n");
+}
+
+void test_sendfile(void)
+{
+    const char *path = "sendfile_src";
+    const char *data = "sendfile data";
+    char buf[32] = { 0 };
+    off_t off = 4;
+    int fds[2];
+    int fd;
+
+    fd = open(path, O_RDWR | O_CREAT | O_TRUNC, 0644);
+    if (fd < 0 || write(fd, data, strlen(data)) != (ssize_t)strlen(data)
+        || lseek(fd, 0, SEEK_SET) != 0 || pipe(fds) != 0) {
+        printf("Sendfile setup error!\n");
+        exit(-1);
+    }
+    if (sendfile(fds[1], fd, &off, sizeof(buf)) != (ssize_t)strlen(data) - 4
+        || off != (off_t)strlen(data)) {
+        printf("Sendfile with an offset error!\n");
+        exit(-1);
+    }
+    if (read(fds[0], buf, sizeof(buf)) != (ssize_t)strlen(data) - 4 || strcmp(buf, data + 4) != 0) {
+        printf("Sendfile sent the wrong data: %s\n", buf);
+        exit(-1);
+    }
+    /* Without an offset the file position is used and advanced. */
+    memset(buf, 0, sizeof(buf));
+    if (sendfile(fds[1], fd, NULL, 4) != 4 || lseek(fd, 0, SEEK_CUR) != 4
+        || read(fds[0], buf, sizeof(buf)) != 4 || strcmp(buf, "send") != 0) {
+        printf("Sendfile from the file position error!\n");
+        exit(-1);
+    }
+    /* A pipe cannot take back the bytes that were not written. */
+    if (sendfile(fd, fds[0], NULL, 4) != -1 || errno != ESPIPE) {
+        printf("Sendfile from a pipe without an offset error!\n");
+        exit(-1);
+    }
+    close(fds[0]);
+    close(fds[1]);
+    close(fd);
+    unlink(path);
+    printf("Sendfile ok!\n");
+}
+
+void test_select(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    struct timeval tv = { 0, 0 };
+    fd_set rfds, wfds;
+    int fds[2];
+    int status = 0;
+    int tid;
+    char c;
+
+    if (pipe(fds) != 0) {
+        printf("Pipe error!\n");
+        exit(-1);
+    }
+    FD_ZERO(&rfds);
+    FD_SET(fds[0], &rfds);
+    FD_ZERO(&wfds);
+    FD_SET(fds[1], &wfds);
+    if (select(fds[1] + 1, &rfds, &wfds, NULL, &tv) != 1
+        || FD_ISSET(fds[0], &rfds) || !FD_ISSET(fds[1], &wfds)) {
+        printf("Select should only see the pipe write end ready!\n");
+        exit(-1);
+    }
+
+    tid = clone(pipe_writer, stack + sizeof(stack), flags, &fds[1]);
+    if (tid <= 0) {
+        printf("Select clone error!\n");
+        exit(-1);
+    }
+    FD_ZERO(&rfds);
+    FD_SET(fds[0], &rfds);
+    tv.tv_sec = 5;
+    if (select(fds[0] + 1, &rfds, NULL, NULL, &tv) != 1 || !FD_ISSET(fds[0], &rfds)) {
+        printf("Select did not see the pipe become readable!\n");
+        exit(-1);
+    }
+    if (read(fds[0], &c, 1) != 1 || c != 'x') {
+        printf("Select read error!\n");
+        exit(-1);
+    }
+    wait4(tid, &status, __WALL, NULL);
+    close(fds[0]);
+    close(fds[1]);
+    printf("Select ok!\n");
+}
+
+static int futex_waker(void *arg)
+{
+    volatile int *word = arg;
+
+    for (int i = 0; i < 10; i++) {
+        sched_yield();
+    }
+    *word = 1;
+    syscall(SYS_futex, word, FUTEX_WAKE, 1, NULL, NULL, 0);
+    return 0;
+}
+
+void test_futex(void)
+{
+    static char stack[16384] __attribute__((aligned(16)));
+    static volatile int word;
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+    struct timespec ts = { 0, 1000000 };
+    int status = 0;
+    int tid;
+
+    word = 0;
+    if (syscall(SYS_futex, &word, FUTEX_WAIT, 1, NULL, NULL, 0) != -1 || errno != EAGAIN) {
+        printf("Futex wait on a changed word should fail with EAGAIN!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_futex, &word, FUTEX_WAIT, 0, &ts, NULL, 0) != -1 || errno != ETIMEDOUT) {
+        printf("Futex wait should time out!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_futex, (void *)0x1000, FUTEX_WAIT, 0, NULL, NULL, 0) != -1 || errno != EFAULT) {
+        printf("Futex on an unmapped address should fail with EFAULT!\n");
+        exit(-1);
+    }
+    if (syscall(SYS_futex, &word, FUTEX_WAKE, 1, NULL, NULL, 0) != 0) {
+        printf("Futex wake without waiters should wake nobody!\n");
+        exit(-1);
+    }
+
+    tid = clone(futex_waker, stack + sizeof(stack), flags, (void *)&word);
+    if (tid <= 0) {
+        printf("Futex clone error!\n");
+        exit(-1);
+    }
+    while (word == 0) {
+        if (syscall(SYS_futex, &word, FUTEX_WAIT_PRIVATE, 0, NULL, NULL, 0) != 0 && errno != EAGAIN) {
+            printf("Futex wait error!\n");
+            exit(-1);
+        }
+    }
+    wait4(tid, &status, __WALL, NULL);
+    printf("Futex ok!\n");
+}
+
+void test_high_fd(void)
+{
+    struct alloc_stats before, after;
+    int fd;
+
+    syscall(SYS_alloc_stats, &before);
+    if (dup3(STDOUT_FILENO, 1000, 0) != 1000) {
+        printf("Dup3 to fd 1000 error!\n");
+        exit(-1);
+    }
+    if (fcntl(1000, F_GETFD) != 0 || write(1000, "", 0) != 0) {
+        printf("Fd 1000 should be open!\n");
+        exit(-1);
+    }
+    syscall(SYS_alloc_stats, &after);
+    /* Only the new descriptor is stored, not the range below it. */
+    if (after.used > before.used + 4096) {
+        printf("Fd 1000 took too much memory: used %lu -> %lu\n", before.used, after.used);
+        exit(-1);
+    }
+    fd = dup(STDOUT_FILENO);
+    if (fd < 0 || fd >= 1000) {
+        printf("Dup should take the lowest free fd, got %d!\n", fd);
+        exit(-1);
+    }
+    close(fd);
+    if (close(1000) != 0 || fcntl(1000, F_GETFD) != -1 || errno != EBADF) {
+        printf("Close of fd 1000 error!\n");
+        exit(-1);
+    }
+    printf("High fd ok!\n");
+}
+
+void test_errno_mapping(const char *fname)
+{
+    if (chdir(fname) != -1 || errno != ENOTDIR) {
+        printf("Chdir to a file should fail with ENOTDIR!\n");
+        exit(-1);
+    }
+    if (mkdir("no_such_dir/sub", 0755) != -1 || errno != ENOENT) {
+        printf("Mkdir under a missing directory should fail with ENOENT!\n");
+        exit(-1);
+    }
+    if (mmap(NULL, 1UL << 40, PROT_READ, MAP_PRIVATE|MAP_ANONYMOUS, -1, 0) != MAP_FAILED
+        || errno != ENOMEM) {
+        printf("Oversized mmap should fail with ENOMEM!\n");
+        exit(-1);
+    }
+    printf("Errno mapping ok!\n");
+}
+
+/* Maps `len` bytes of `fd` at `off` and compares them with `data`. */
+static void check_file_map(int fd, const char *data, long size, long off, long len)
+{
+    long page_end = (len + 4095) & ~4095L;
+    char *addr;
+
+    addr = mmap(NULL, len, PROT_READ, MAP_PRIVATE, fd, off);
+    if (addr == MAP_FAILED) {
+        printf("Mmap of offset %ld length %ld error!\n", off, len);
+        exit(-1);
+    }
+    for (long i = 0; i < page_end; i++) {
+        char expected = off + i < size && i < len ? data[off + i] : 0;
+        if (addr[i] != expected) {
+            printf("Mmap of offset %ld length %ld differs at byte %ld!\n", off, len, i);
+            exit(-1);
+        }
+    }
+    munmap(addr, len);
+    if (lseek(fd, 0, SEEK_CUR) != 123) {
+        printf("Mmap of offset %ld moved the file position!\n", off);
+        exit(-1);
+    }
+}
+
+void test_mmap_file_harness(void)
+{
+    const char *fname = "mmap_harness";
+    const long size = 2 * 4096 + 1000;
+    static char data[2 * 4096 + 1000];
+    int fd;
+
+    for (long i = 0; i < size; i++) {
+        data[i] = (char)(i * 7 + 3);
+    }
+    fd = open(fname, O_RDWR|O_CREAT|O_TRUNC, 0600);
+    if (fd < 0 || write(fd, data, size) != size || lseek(fd, 123, SEEK_SET) != 123) {
+        printf("Mmap harness file error!\n");
+        exit(-1);
+    }
+    /* The whole file, with a length that is not a page multiple. */
+    check_file_map(fd, data, size, 0, size);
+    /* A short piece from the middle. */
+    check_file_map(fd, data, size, 4096, 3000);
+    /* The last page, of which only 1000 bytes are in the file. */
+    check_file_map(fd, data, size, 2 * 4096, 4096);
+    close(fd);
+    unlink(fname);
+    printf("Mmap file harness ok!\n");
+}
+
+static volatile int exit_group_started;
+
+static int busy_child(void *arg)
+{
+    volatile unsigned long *counter = arg;
+
+    __atomic_fetch_add(&exit_group_started, 1, __ATOMIC_SEQ_CST);
+    for (;;) {
+        (*counter)++;
+    }
+    return 0;
+}
+
+static int blocked_child(void *arg)
+{
+    int *word = arg;
+
+    __atomic_fetch_add(&exit_group_started, 1, __ATOMIC_SEQ_CST);
+    for (;;) {
+        syscall(SYS_futex, word, FUTEX_WAIT, 0, NULL, NULL, 0);
+    }
+    return 0;
+}
+
+/*
+ * Leaves one thread spinning in user space without syscalls and one blocked
+ * in a futex wait. Returning from main must stop both, or the kernel never
+ * reports that all user threads exited.
+ */
+void test_exit_group(void)
+{
+    static char busy_stack[16384] __attribute__((aligned(16)));
+    static char blocked_stack[16384] __attribute__((aligned(16)));
+    static volatile unsigned long counter;
+    static int word;
+    int flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND | CLONE_THREAD;
+
+    if (clone(busy_child, busy_stack + sizeof(busy_stack), flags, (void *)&counter) <= 0
+        || clone(blocked_child, blocked_stack + sizeof(blocked_stack), flags, &word) <= 0) {
+        printf("Exit group clone error!\n");
+        exit(-1);
+    }
+    while (exit_group_started < 2) {
+        sched_yield();
+    }
+    printf("Exit group leaves 2 threads running\n");
+}
+
 int main()
 {
     int fd;
@@ -54,6 +2219,71 @@ int main()
 
     create_file(fname);
     verify_file(fname);
+    test_mremap();
+    test_brk();
+    test_madvise();
+    test_getpid();
+    test_clock_gettime();
+    test_nanosleep();
+    test_sched_yield();
+    test_getrandom();
+    test_uname();
+    test_fstat(fname);
+    test_lseek(fname);
+    test_dup(fname);
+    test_pipe();
+    test_fcntl();
+    test_readv(fname);
+    test_pread(fname);
+    test_ftruncate();
+    test_fsync(fname);
+    test_chdir();
+    test_mkdir();
+    test_rename();
+    test_access(fname);
+    test_readlink(fname);
+    test_getdents();
+    test_ioctl(fname);
+    test_fionbio();
+    test_writev();
+    test_syscall_count();
+    test_enosys();
+    test_rlimit();
+    test_getuid();
+    test_clock_nanosleep();
+    test_times();
+    test_sysinfo();
+    test_mlock();
+    test_statfs(fname);
+    test_umask();
+    test_clone();
+    test_wait4();
+    test_kill();
+    test_sigaction();
+    test_bad_buffer(fname);
+    test_copy_user();
+    test_user_path(fname);
+    test_maps();
+    test_alloc_stats();
+    test_short_read();
+    test_poll();
+    test_clock_getres();
+    test_open_mode();
+    test_openat(fname);
+    test_close();
+    test_exit_status();
+    test_membarrier();
+    test_mmap_fd_checks();
+    test_map_populate();
+    test_map_hugetlb();
+    test_mmap_zeroed();
+    test_sendfile();
+    test_select();
+    test_futex();
+    test_high_fd();
+    test_errno_mapping(fname);
+    test_mmap_file_harness();
+    test_exit_group();
 
     printf("MapFile ok!\n");
     return 0;
@@ -0,0 +1,128 @@
+//! Compares [`axstd::collections::HashMap`] with a `BTreeMap` baseline, and
+//! its entry API with a `contains_key`/`insert` pair for counting keys.
+//!
+//! Benches run on the host with std, so build them with the host target:
+//!
+//! ```sh
+//! cargo bench -p axstd --features alloc --target x86_64-unknown-linux-gnu
+//! ```
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+use axstd::collections::HashMap;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const SIZES: [u64; 2] = [1_000, 100_000];
+
+fn hash_map(n: u64) -> HashMap<u64, u64> {
+    let mut map = HashMap::new();
+    for i in 0..n {
+        map.insert(i, i);
+    }
+    map
+}
+
+fn btree_map(n: u64) -> BTreeMap<u64, u64> {
+    (0..n).map(|i| (i, i)).collect()
+}
+
+fn bench_insert(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert");
+    for n in SIZES {
+        group.throughput(Throughput::Elements(n));
+        group.bench_with_input(BenchmarkId::new("HashMap", n), &n, |b, &n| {
+            b.iter(|| hash_map(black_box(n)))
+        });
+        group.bench_with_input(BenchmarkId::new("BTreeMap", n), &n, |b, &n| {
+            b.iter(|| btree_map(black_box(n)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_lookup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("lookup");
+    for n in SIZES {
+        group.throughput(Throughput::Elements(n));
+        let map = hash_map(n);
+        group.bench_with_input(BenchmarkId::new("HashMap", n), &n, |b, &n| {
+            b.iter(|| (0..n).filter(|k| map.get(black_box(k)).is_some()).count())
+        });
+        let map = btree_map(n);
+        group.bench_with_input(BenchmarkId::new("BTreeMap", n), &n, |b, &n| {
+            b.iter(|| (0..n).filter(|k| map.get(black_box(k)).is_some()).count())
+        });
+    }
+    group.finish();
+}
+
+fn bench_iterate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("iterate");
+    for n in SIZES {
+        group.throughput(Throughput::Elements(n));
+        let map = hash_map(n);
+        group.bench_function(BenchmarkId::new("HashMap", n), |b| {
+            b.iter(|| map.iter().map(|(_, v)| black_box(*v)).sum::<u64>())
+        });
+        let map = btree_map(n);
+        group.bench_function(BenchmarkId::new("BTreeMap", n), |b| {
+            b.iter(|| map.iter().map(|(_, v)| black_box(*v)).sum::<u64>())
+        });
+    }
+    group.finish();
+}
+
+/// A stream of `n` keys with many repeats, like the words of a text.
+fn key_stream(n: u64) -> Vec<u64> {
+    let mut x = 0x9e37_79b9_7f4a_7c15_u64;
+    (0..n)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x % (n / 10).max(1)
+        })
+        .collect()
+}
+
+fn bench_word_count(c: &mut Criterion) {
+    let mut group = c.benchmark_group("word_count");
+    for n in SIZES {
+        group.throughput(Throughput::Elements(n));
+        let keys = key_stream(n);
+        group.bench_with_input(BenchmarkId::new("entry", n), &keys, |b, keys| {
+            b.iter(|| {
+                let mut counts = HashMap::new();
+                for &k in keys {
+                    *counts.entry(black_box(k)).or_insert(0u32) += 1;
+                }
+                counts
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("contains_key", n), &keys, |b, keys| {
+            b.iter(|| {
+                let mut counts = HashMap::new();
+                for &k in keys {
+                    let k = black_box(k);
+                    if counts.contains_key(&k) {
+                        *counts.get_mut(&k).unwrap() += 1;
+                    } else {
+                        counts.insert(k, 1u32);
+                    }
+                }
+                counts
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_insert,
+    bench_lookup,
+    bench_iterate,
+    bench_word_count
+);
+criterion_main!(benches);
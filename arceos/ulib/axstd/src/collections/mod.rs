@@ -6,13 +6,19 @@
 #[cfg(feature = "alloc")]
 pub use alloc::collections::*;
 
+#[cfg(all(test, feature = "alloc"))]
+mod tests;
+
 /// A hash map implemented with quadratic probing and SIMD lookup.
 ///
 /// This is a wrapper around `hashbrown::HashMap` that provides a `new()` method
 /// compatible with the standard library's `HashMap` interface.
+///
+/// Keys are hashed with `hashbrown::DefaultHashBuilder` unless another
+/// hasher `S` is given with [`HashMap::with_hasher`].
 #[cfg(feature = "alloc")]
-pub struct HashMap<K, V> {
-    inner: hashbrown::HashMap<K, V, hashbrown::DefaultHashBuilder>,
+pub struct HashMap<K, V, S = hashbrown::DefaultHashBuilder> {
+    inner: hashbrown::HashMap<K, V, S>,
 }
 
 #[cfg(feature = "alloc")]
@@ -55,6 +61,36 @@ impl<K, V> HashMap<K, V> {
             ),
         }
     }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S> HashMap<K, V, S> {
+    /// Creates an empty `HashMap` which will use the given hash builder to
+    /// hash keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use axstd::collections::HashMap;
+    /// use hashbrown::DefaultHashBuilder;
+    /// let mut map: HashMap<&str, i32, _> = HashMap::with_hasher(DefaultHashBuilder::default());
+    /// map.insert("a", 1);
+    /// ```
+    #[inline]
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            inner: hashbrown::HashMap::with_hasher(hasher),
+        }
+    }
+
+    /// Creates an empty `HashMap` with the specified capacity, using
+    /// `hasher` to hash the keys.
+    #[inline]
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            inner: hashbrown::HashMap::with_capacity_and_hasher(capacity, hasher),
+        }
+    }
 
     /// Inserts a key-value pair into the map.
     ///
@@ -67,6 +103,7 @@ impl<K, V> HashMap<K, V> {
     pub fn insert(&mut self, k: K, v: V) -> Option<V>
     where
         K: core::hash::Hash + core::cmp::Eq,
+        S: core::hash::BuildHasher,
     {
         self.inner.insert(k, v)
     }
@@ -77,16 +114,51 @@ impl<K, V> HashMap<K, V> {
     where
         K: core::borrow::Borrow<Q> + core::hash::Hash + core::cmp::Eq,
         Q: core::hash::Hash + core::cmp::Eq,
+        S: core::hash::BuildHasher,
     {
         self.inner.get(k)
     }
 
+    /// Returns `true` if the map contains a value for the specified key.
+    #[inline]
+    pub fn contains_key<Q: ?Sized>(&self, k: &Q) -> bool
+    where
+        K: core::borrow::Borrow<Q> + core::hash::Hash + core::cmp::Eq,
+        Q: core::hash::Hash + core::cmp::Eq,
+        S: core::hash::BuildHasher,
+    {
+        self.inner.contains_key(k)
+    }
+
+    /// Gets the given key's corresponding entry in the map for in-place
+    /// manipulation, hashing the key only once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use axstd::collections::HashMap;
+    /// let mut counts: HashMap<&str, u32> = HashMap::new();
+    /// *counts.entry("a").or_insert(0) += 1;
+    /// ```
+    #[inline]
+    pub fn entry(
+        &mut self,
+        key: K,
+    ) -> hashbrown::hash_map::Entry<'_, K, V, S>
+    where
+        K: core::hash::Hash + core::cmp::Eq,
+        S: core::hash::BuildHasher,
+    {
+        self.inner.entry(key)
+    }
+
     /// Returns a mutable reference to the value corresponding to the key.
     #[inline]
     pub fn get_mut<Q: ?Sized>(&mut self, k: &Q) -> Option<&mut V>
     where
         K: core::borrow::Borrow<Q> + core::hash::Hash + core::cmp::Eq,
         Q: core::hash::Hash + core::cmp::Eq,
+        S: core::hash::BuildHasher,
     {
         self.inner.get_mut(k)
     }
@@ -98,6 +170,7 @@ impl<K, V> HashMap<K, V> {
     where
         K: core::borrow::Borrow<Q> + core::hash::Hash + core::cmp::Eq,
         Q: core::hash::Hash + core::cmp::Eq,
+        S: core::hash::BuildHasher,
     {
         self.inner.remove(k)
     }
@@ -117,6 +190,27 @@ impl<K, V> HashMap<K, V> {
         self.inner.iter_mut()
     }
 
+    /// An iterator visiting all keys in arbitrary order.
+    /// The iterator element type is `&'a K`.
+    #[inline]
+    pub fn keys(&self) -> hashbrown::hash_map::Keys<'_, K, V> {
+        self.inner.keys()
+    }
+
+    /// An iterator visiting all values in arbitrary order.
+    /// The iterator element type is `&'a V`.
+    #[inline]
+    pub fn values(&self) -> hashbrown::hash_map::Values<'_, K, V> {
+        self.inner.values()
+    }
+
+    /// An iterator visiting all values mutably in arbitrary order.
+    /// The iterator element type is `&'a mut V`.
+    #[inline]
+    pub fn values_mut(&mut self) -> hashbrown::hash_map::ValuesMut<'_, K, V> {
+        self.inner.values_mut()
+    }
+
     /// Returns the number of elements in the map.
     #[inline]
     pub fn len(&self) -> usize {
@@ -128,20 +222,28 @@ impl<K, V> HashMap<K, V> {
     pub fn is_empty(&self) -> bool {
         self.inner.is_empty()
     }
+
+    /// Clears the map, removing all key-value pairs. Keeps the allocated
+    /// memory for reuse.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
 }
 
 #[cfg(feature = "alloc")]
-impl<K, V> Default for HashMap<K, V> {
+impl<K, V, S: Default> Default for HashMap<K, V, S> {
     #[inline]
     fn default() -> Self {
-        Self::new()
+        Self::with_hasher(S::default())
     }
 }
 
 #[cfg(feature = "alloc")]
-impl<K, V> core::ops::Index<&K> for HashMap<K, V>
+impl<K, V, S> core::ops::Index<&K> for HashMap<K, V, S>
 where
     K: core::hash::Hash + core::cmp::Eq,
+    S: core::hash::BuildHasher,
 {
     type Output = V;
 
@@ -150,3 +252,79 @@ where
         &self.inner[key]
     }
 }
+
+#[cfg(feature = "alloc")]
+impl<K, V, S> IntoIterator for HashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = hashbrown::hash_map::IntoIter<K, V>;
+
+    /// Creates a consuming iterator visiting all key-value pairs in
+    /// arbitrary order.
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V, S> IntoIterator for &'a HashMap<K, V, S> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = hashbrown::hash_map::Iter<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V, S> IntoIterator for &'a mut HashMap<K, V, S> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = hashbrown::hash_map::IterMut<'a, K, V>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.iter_mut()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S> FromIterator<(K, V)> for HashMap<K, V, S>
+where
+    K: core::hash::Hash + core::cmp::Eq,
+    S: core::hash::BuildHasher + Default,
+{
+    #[inline]
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<K, V, S> Extend<(K, V)> for HashMap<K, V, S>
+where
+    K: core::hash::Hash + core::cmp::Eq,
+    S: core::hash::BuildHasher,
+{
+    /// Inserts all pairs from the iterator, replacing the values of keys
+    /// that are already present.
+    #[inline]
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        self.inner.extend(iter)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, K, V, S> Extend<(&'a K, &'a V)> for HashMap<K, V, S>
+where
+    K: core::hash::Hash + core::cmp::Eq + Copy,
+    V: Copy,
+    S: core::hash::BuildHasher,
+{
+    #[inline]
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        self.inner.extend(iter)
+    }
+}
@@ -0,0 +1,233 @@
+use super::HashMap;
+
+/// Keys are drawn from a small range so that lookups and removals hit.
+const KEY_RANGE: u64 = 64;
+const NUM_SEEDS: u64 = 32;
+const NUM_OPS: usize = 5000;
+
+/// xorshift64, so that failures can be replayed from the seed.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+fn run_sequence(seed: u64) {
+    let mut rng = Rng(seed);
+    let mut map = HashMap::new();
+    let mut model = std::collections::HashMap::new();
+
+    for step in 0..NUM_OPS {
+        let key = rng.next() % KEY_RANGE;
+        match rng.next() % 5 {
+            0 | 1 => {
+                let value = rng.next();
+                assert_eq!(
+                    map.insert(key, value),
+                    model.insert(key, value),
+                    "seed {seed} step {step}: insert({key})"
+                );
+            }
+            2 => assert_eq!(
+                map.get(&key),
+                model.get(&key),
+                "seed {seed} step {step}: get({key})"
+            ),
+            3 => assert_eq!(
+                map.remove(&key),
+                model.remove(&key),
+                "seed {seed} step {step}: remove({key})"
+            ),
+            _ => {
+                let (v, w) = (map.get_mut(&key), model.get_mut(&key));
+                assert_eq!(v.is_some(), w.is_some(), "seed {seed} step {step}: get_mut({key})");
+                if let (Some(v), Some(w)) = (v, w) {
+                    *v = v.wrapping_add(1);
+                    *w = w.wrapping_add(1);
+                }
+            }
+        }
+        assert_eq!(map.len(), model.len(), "seed {seed} step {step}: len");
+        assert_eq!(map.is_empty(), model.is_empty(), "seed {seed} step {step}: is_empty");
+    }
+
+    let mut entries: Vec<_> = map.iter().map(|(&k, &v)| (k, v)).collect();
+    let mut expected: Vec<_> = model.into_iter().collect();
+    entries.sort_unstable();
+    expected.sort_unstable();
+    assert_eq!(entries, expected, "seed {seed}: entries");
+}
+
+#[test]
+fn test_against_std_hashmap() {
+    for seed in 1..=NUM_SEEDS {
+        run_sequence(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    }
+}
+
+/// Counts with the entry API, hashing each key once.
+fn count_with_entry(keys: &[u64]) -> HashMap<u64, u32> {
+    let mut counts = HashMap::new();
+    for &k in keys {
+        *counts.entry(k).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Counts with a separate lookup before each update.
+fn count_with_lookup(keys: &[u64]) -> HashMap<u64, u32> {
+    let mut counts = HashMap::new();
+    for &k in keys {
+        if counts.contains_key(&k) {
+            *counts.get_mut(&k).unwrap() += 1;
+        } else {
+            counts.insert(k, 1);
+        }
+    }
+    counts
+}
+
+#[test]
+fn test_entry_word_count() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let keys: Vec<u64> = (0..NUM_OPS).map(|_| rng.next() % KEY_RANGE).collect();
+    let by_entry = count_with_entry(&keys);
+    let by_lookup = count_with_lookup(&keys);
+
+    assert_eq!(by_entry.len(), by_lookup.len());
+    for (k, v) in by_entry.iter() {
+        assert_eq!(by_lookup.get(k), Some(v), "count of {k}");
+    }
+    assert_eq!(by_entry.iter().map(|(_, &v)| v as usize).sum::<usize>(), keys.len());
+}
+
+#[test]
+fn test_contains_key() {
+    let mut map = HashMap::new();
+    map.insert(1u64, "one");
+    assert!(map.contains_key(&1));
+    assert!(!map.contains_key(&2));
+    map.remove(&1);
+    assert!(!map.contains_key(&1));
+}
+
+#[test]
+fn test_keys_values() {
+    let mut map = HashMap::new();
+    for k in [3u64, 1, 2] {
+        map.insert(k, k * 10);
+    }
+
+    let mut keys: Vec<_> = map.keys().copied().collect();
+    keys.sort_unstable();
+    assert_eq!(keys, [1, 2, 3]);
+    assert!(map.keys().all(|k| map.contains_key(k)));
+
+    for v in map.values_mut() {
+        *v += 1;
+    }
+    let mut values: Vec<_> = map.values().copied().collect();
+    values.sort_unstable();
+    assert_eq!(values, [11, 21, 31]);
+}
+
+fn sample_map() -> HashMap<u64, u64> {
+    let mut map = HashMap::new();
+    for k in 0..8 {
+        map.insert(k, k * k);
+    }
+    map
+}
+
+#[test]
+fn test_into_iter_ref() {
+    let map = sample_map();
+    let mut sum = 0;
+    for (k, v) in &map {
+        assert_eq!(*v, k * k);
+        sum += v;
+    }
+    assert_eq!(sum, (0..8).map(|k| k * k).sum());
+}
+
+#[test]
+fn test_into_iter_mut() {
+    let mut map = sample_map();
+    for (k, v) in &mut map {
+        *v += k;
+    }
+    assert!((0..8).all(|k| map.get(&k) == Some(&(k * k + k))));
+}
+
+#[test]
+fn test_into_iter_owned() {
+    let mut entries: Vec<(u64, u64)> = sample_map().into_iter().collect();
+    entries.sort_unstable();
+    assert_eq!(entries, (0..8).map(|k| (k, k * k)).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_from_iter() {
+    let pairs = vec![(1u64, 'a'), (2, 'b'), (1, 'c')];
+    let map: HashMap<_, _> = pairs.into_iter().collect();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(&1), Some(&'c'));
+    assert_eq!(map.get(&2), Some(&'b'));
+}
+
+#[test]
+fn test_extend() {
+    let mut map = sample_map();
+    map.extend([(7, 0), (8, 64)]);
+    assert_eq!(map.len(), 9);
+    assert_eq!(map.get(&7), Some(&0));
+
+    let extra = [(9u64, 81u64), (10, 100)];
+    map.extend(extra.iter().map(|(k, v)| (k, v)));
+    assert_eq!(map.len(), 11);
+    assert_eq!(map.get(&10), Some(&100));
+}
+
+#[test]
+fn test_clear() {
+    let mut map = sample_map();
+    assert!(!map.is_empty());
+    map.clear();
+    assert_eq!(map.len(), 0);
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+
+    assert_eq!(map.insert(3, 9), None);
+    assert_eq!(map.len(), 1);
+    assert!(!map.is_empty());
+}
+
+/// std's `DefaultHasher` built by `Default` uses fixed keys.
+type FixedState = std::hash::BuildHasherDefault<std::collections::hash_map::DefaultHasher>;
+
+fn fixed_map(keys: &[u64]) -> HashMap<u64, u64, FixedState> {
+    let mut map = HashMap::with_capacity_and_hasher(keys.len(), FixedState::default());
+    for &k in keys {
+        map.insert(k, k + 1);
+    }
+    map
+}
+
+#[test]
+fn test_with_hasher() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let keys: Vec<u64> = (0..NUM_OPS).map(|_| rng.next()).collect();
+    let (a, b) = (fixed_map(&keys), fixed_map(&keys));
+    assert!(a.iter().eq(b.iter()));
+    assert!(keys.iter().all(|k| a.get(k) == Some(&(k + 1))));
+
+    let mut empty: HashMap<u64, u64, FixedState> = HashMap::with_hasher(FixedState::default());
+    assert!(empty.is_empty());
+    empty.extend(&a);
+    assert_eq!(empty.len(), a.len());
+}
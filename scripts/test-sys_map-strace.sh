@@ -0,0 +1,33 @@
+#!/bin/bash
+
+tmp_file=strace_test_output.txt
+grep_contents=(
+    'openat(AT_FDCWD, "test_file", O_RDONLY, 0) = 3'
+    'close(3) = 0'
+    'exit_group(0) = ?'
+)
+
+cd arceos/ || exit
+
+rm pflash.img -f
+rm disk.img -f
+
+make pflash_img
+make disk_img
+
+make payload
+./update_disk.sh payload/mapfile_c/mapfile
+
+make run A=exercises/sys_map/ BLK=y APP_FEATURES=strace 2>/dev/null | tee $tmp_file
+
+for content in "${grep_contents[@]}"; do
+    if ! grep -Fqa "$content" ./$tmp_file; then
+        rm -rf $tmp_file
+        echo "sys_map strace failed: missing \"$content\""
+        exit 1
+    fi
+done
+
+rm -rf $tmp_file
+echo "sys_map strace pass"
+exit 0